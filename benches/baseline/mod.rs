@@ -0,0 +1,374 @@
+//The nom parser the crate had before parsing went line by line, kept as it was so the
+//benchmarks can compare against it. Only the tests were dropped, and `extra` added to the
+//header, which it didn't have.
+use noisy_float::prelude::*;
+use nom::character::complete::{
+    alphanumeric1, line_ending, multispace1, not_line_ending, space0, space1,
+};
+use nom::number::complete::double;
+use nom::{Err, IResult};
+use tsplib::*;
+
+//Gives us a parser called kv() that takes a key to look for, and will return
+//the value in  a string of "KEY: VALUE"
+named_args!(kv<'a>(key: &'a str)<&'a str, &'a str>,
+   do_parse!(
+        tag!(key) >>
+        space0 >>
+        tag!(":") >>
+        space0 >>
+        value: not_line_ending >>
+        line_ending >>
+        (value.trim_end())
+    )
+);
+
+//Calls kv and then uses str::parse::<T> to parse the VALUE to the type you want
+fn kv_parse<'a, T>(input: &'a str, key: &'a str) -> IResult<&'a str, T>
+where
+    T: std::str::FromStr,
+{
+    kv(input, key).and_then(|(i, v)| match str::parse::<T>(v) {
+        Ok(tv) => Ok((i, tv)),
+        Err(_a) => Err(Err::Error((i, nom::error::ErrorKind::ParseTo))),
+    })
+}
+
+//This version of kv! looks for the first word to the right of the colon and calls
+//that the value. Needed for an odd case where someone wrote TYPE: TSP (Foo Bar)
+//and we want to treat that as TYPE: TSP.
+named_args!(kv_single<'a>(key: &'a str)<&'a str, &'a str>,
+   do_parse!(
+        tag!(key) >>
+        space0 >>
+        tag!(":") >>
+        space0 >>
+        value: alphanumeric1 >>
+        _ignore: not_line_ending >>
+        opt!(line_ending) >>
+        (value.trim_end())
+    )
+);
+//Copy of kv_parse for kv_single
+fn kv_parse_single<'a, T>(input: &'a str, key: &'a str) -> IResult<&'a str, T>
+where
+    T: std::str::FromStr,
+{
+    kv_single(input, key).and_then(|(i, v)| match str::parse::<T>(v) {
+        Ok(tv) => Ok((i, tv)),
+        Err(_a) => Err(Err::Error((i, nom::error::ErrorKind::ParseTo))),
+    })
+}
+
+//TSPLIB defines all of these key:value pairs in its header, and they can be set
+//in any order.
+//Cool note: the way we map! the result of permutation! lets kv_parse! figure out
+//the wanted return type, and then it calls the right string -> Data type
+//conversion automatically.
+//The reason that COMMENT is different is that 1 of the problems has multiple
+//COMMENT lines as one big multiline comment, and I wanted to support that.
+//It doesn't need the call to complete! because many1 can take Incomplete, but
+//it looks weird to have many1() + ?. I tried many0 but hit some macro issues.
+fn parse_header(input: &str) -> IResult<&str, TSPLMeta> {
+    map!(
+        input,
+        complete!(permutation!(
+            complete!(call!(kv_parse, "NAME"))?,
+            complete!(call!(kv_parse_single, "TYPE")),
+            many1!(call!(kv_parse, "COMMENT"))?,
+            complete!(call!(kv_parse_single, "DIMENSION")),
+            complete!(call!(kv_parse, "EDGE_WEIGHT_TYPE"))?,
+            complete!(call!(kv_parse_single, "CAPACITY"))?,
+            complete!(call!(kv_parse, "EDGE_WEIGHT_FORMAT"))?,
+            complete!(call!(kv_parse, "EDGE_DATA_FORMAT"))?,
+            complete!(call!(kv_parse, "DISPLAY_DATA_TYPE"))?,
+            complete!(call!(kv_parse, "NODE_COORD_TYPE"))?
+        )),
+        |(
+            name,
+            problem_type,
+            comments,
+            dimension,
+            ewt,
+            capacity,
+            edge_weight_format,
+            edge_data_format,
+            ddt,
+            nct,
+        ): (
+            Option<String>,
+            ProblemType,
+            Option<Vec<String>>,
+            u32,
+            Option<EdgeWeightType>,
+            Option<u32>,
+            Option<EdgeWeightFormat>,
+            Option<EdgeDataFormat>,
+            Option<DisplayDataType>,
+            Option<NodeCoordType>,
+        )| TSPLMeta {
+            name: name.unwrap_or_else(|| "".to_string()),
+            problem_type,
+            comment: comments
+                .map(|comments| comments.join(""))
+                .unwrap_or_else(|| "".to_string()),
+            dimension,
+            capacity,
+            edge_weight_type: ewt.unwrap_or(EdgeWeightType::EUC_2D),
+            edge_data_format,
+            edge_weight_format,
+            node_coord_type: nct.unwrap_or(NodeCoordType::NO_COORDS),
+            display_data_type: ddt.unwrap_or(DisplayDataType::NO_DISPLAY),
+            extra: vec![],
+        }
+    )
+}
+
+fn numbers_on_line(input: &str) -> IResult<&str, Vec<f64>> {
+    separated_list!(input, space1, double)
+}
+
+/*
+The data sections look like:
+NODE_DATA_SECTION
+1 3.2 4.0
+2 7.4 8.1
+3 5.2 6.9
+...
+or
+EDGE_WEIGHT_DATA
+1 3 4 5 0 18 3
+5 7 1 9 3 8 2
+...
+So we're abstracting that into a parser that looks for a section title, a newline,
+and then a bunch of lines that contain whitespace delimited numbers.
+We're going to unfortunately (but not wrongly) cast them to floats at first,
+And then call the given line_parser function on each line, which will turn lines of numbers
+into the right data we want.
+    */
+fn get_section<'a, T>(
+    input: &'a str,
+    section_title: &'a str,
+    line_parser: fn(Vec<f64>) -> Option<T>,
+) -> IResult<&'a str, Vec<T>> {
+    do_parse!(
+        input,
+        tag!(section_title)
+            >> line_ending
+            >> space0
+            >> payload: separated_list!(multispace1, map_opt!(numbers_on_line, line_parser))
+            >> space0
+            >> opt!(line_ending)
+            >> opt!(complete!(tag!("-1")))
+            >> opt!(complete!(tag!("EOF\n")))
+            >> (payload)
+    )
+}
+
+//These functions parse individual lines of numbers into different domain-level types
+
+fn parse_depot_vec(input: Vec<f64>) -> Option<usize> {
+    match input.len() {
+        1 => Some(input[0] as usize),
+        _ => None,
+    }
+}
+fn parse_coord2_vec(input: Vec<f64>) -> Option<Coord> {
+    match input.len() {
+        3 => Some(Coord::Coord2(input[0] as i64, n64(input[1]), n64(input[2]))),
+        _ => None,
+    }
+}
+
+fn parse_coord3_vec(input: Vec<f64>) -> Option<Coord> {
+    match input.len() {
+        4 => Some(Coord::Coord3(
+            input[0] as i64,
+            n64(input[1]),
+            n64(input[2]),
+            n64(input[3]),
+        )),
+        _ => None,
+    }
+}
+
+fn parse_demand_vec(input: Vec<f64>) -> Option<Demand> {
+    match input.len() {
+        2 => Some(Demand(input[0] as usize, input[1] as u32)),
+        _ => None,
+    }
+}
+
+fn parse_edge_vec(input: Vec<f64>) -> Option<Edge> {
+    match input.len() {
+        2 => Some((input[0] as usize, input[1] as usize)),
+        _ => None,
+    }
+}
+
+fn parse_tour_vec(input: Vec<f64>) -> Option<Tour> {
+    match input.len() {
+        0 => None,
+        _ => Some(
+            input
+                .into_iter()
+                .map(|i| i as usize)
+                .collect::<Vec<usize>>(),
+        ),
+    }
+}
+
+fn parse_weights_vec(input: Vec<f64>) -> Option<EdgeWeightList> {
+    match input.len() {
+        0 => None,
+        _ => Some(
+            input
+                .into_iter()
+                .map(|i| i as EdgeWeight)
+                .collect::<EdgeWeightList>(),
+        ),
+    }
+}
+
+fn parse_edgedata_vec(input: Vec<f64>) -> Option<EdgeData> {
+    match input.len() {
+        2 => Some(EdgeData::Edge((input[0] as usize, input[1] as usize))),
+        _ => None,
+    }
+}
+
+fn parse_adjacency_vec(input: Vec<f64>) -> Option<EdgeData> {
+    match input.len() {
+        len if len < 2 => None,
+        _ => Some(EdgeData::Adj(
+            input
+                .into_iter()
+                .map(|i| i as usize)
+                .collect::<Vec<usize>>(),
+        )),
+    }
+}
+fn combine_demands(demands: Vec<Demand>, dimension: usize) -> Vec<u32> {
+    let mut res = vec![0; dimension];
+    for demand in demands {
+        res[demand.0 - 1] = demand.1 //TSPLIB counts from 1
+    }
+    res
+}
+
+//The adjacency list format will have the node_id as the first number, and can be
+//in any order. This function will take a Vec<Vec<usize>>, and for each row,
+//use the first number as the node_id, and put the rest of the numbers in that row's
+//adjacency list. //Eg if we have
+// 4 8 5 6
+// 1 8 0
+// 2 8
+//This will return
+// vec![
+//   vec[0, 1, 8]
+//   vec[8]
+//   vec[]
+//   vec[5, 6, 8]
+// ]
+fn combine_adjacencies(
+    edges: Vec<EdgeData>,
+    edf: &EdgeDataFormat,
+    dimension: &usize,
+) -> Vec<EdgeData> {
+    if *edf == EdgeDataFormat::EDGE_LIST {
+        return edges;
+    }
+
+    let mut res: Vec<EdgeData> = vec![EdgeData::Adj(vec![]); *dimension];
+    for edge_row in edges {
+        if let EdgeData::Adj(adj_row) = edge_row {
+            if let Some((node_id, adjacencies)) = adj_row.split_first() {
+                let mut adj_vec = adjacencies.to_vec();
+                adj_vec.sort();
+                res[*node_id - 1] = EdgeData::Adj(adj_vec); //TSPLIB counts from 1
+            }
+        }
+    }
+    res
+}
+fn ignore_vec<T>(input: Vec<f64>) -> Option<T> {
+    println!(
+        "Warning: Going to ignore this row because its format is unknown: {:?}",
+        input
+    );
+    None
+}
+
+fn parse_data_section<'a>(input: &'a str, header: TSPLMeta) -> IResult<&'a str, TSPLProblem> {
+    //Here we should be building a list of sections that we are expecting based
+    //on the header data. At the moment we are making every section optional,
+    //Or silently ignoring data if the format is not set (which is not ideal).
+    let edge_parser = match header.edge_data_format {
+        Some(EdgeDataFormat::ADJ_LIST) => parse_adjacency_vec,
+        Some(EdgeDataFormat::EDGE_LIST) => parse_edgedata_vec,
+        None => ignore_vec, //TODO: omit the EDGE_DATA_SECTION if there is no Format for it
+    };
+    //TODO: Be a bit smarter about parser type here. NO_COORDS can be the type, but the problem can be EUC2D
+    //and it will expect a NODE_COORD_SECTION.
+    let coord_parser = match header.node_coord_type {
+        NodeCoordType::THREED_COORDS => parse_coord3_vec,
+        NodeCoordType::TWOD_COORDS => parse_coord2_vec,
+        NodeCoordType::NO_COORDS => parse_coord2_vec,
+    };
+    map!(
+        input,
+        permutation!(
+            complete!(call!(get_section, "NODE_COORD_SECTION", coord_parser))?,
+            complete!(call!(get_section, "DEPOT_SECTION", parse_depot_vec))?,
+            complete!(call!(get_section, "DEMAND_SECTION", parse_demand_vec))?,
+            complete!(call!(get_section, "EDGE_DATA_SECTION", edge_parser))?,
+            complete!(call!(get_section, "FIXED_EDGES_SECTION", parse_edge_vec))?,
+            complete!(call!(get_section, "DISPLAY_DATA_SECTION", parse_coord2_vec))?, //TODO only call this parser if DISPLAY_DATA_TYPE is TWOD_COORDS
+            complete!(call!(get_section, "TOUR_SECTION", parse_tour_vec))?,
+            complete!(call!(get_section, "EDGE_WEIGHT_SECTION", parse_weights_vec))?
+        ),
+        |(
+            node_coordinates,
+            depots,
+            demands,
+            edges,
+            fixed_edges,
+            display_data,
+            tours,
+            edge_weights,
+        ): (
+            Option<Vec<Coord>>,
+            Option<Vec<usize>>,
+            Option<Vec<Demand>>,
+            Option<Vec<EdgeData>>,
+            Option<Vec<Edge>>,
+            Option<Vec<Coord>>,
+            Option<Vec<Tour>>,
+            Option<Vec<EdgeWeightList>>,
+        )| {
+            TSPLProblem {
+                header: header.clone(),
+                data: TSPLData {
+                    node_coordinates,
+                    depots,
+                    demands: demands.map(|d| combine_demands(d, header.dimension as usize)),
+                    display_data,
+                    edge_weights: edge_weights.map(|ew| ew.concat()), //.and_then(build_distance_matrix),
+                    edges: edges.map(|es| {
+                        combine_adjacencies(
+                            es,
+                            &header.edge_data_format.clone().unwrap(),
+                            &(header.dimension as usize),
+                        )
+                    }),
+                    fixed_edges,
+                    tours,
+                },
+            }
+        }
+    )
+}
+///Parse a `&str` into a `Result<TSPLroblem>`
+pub fn parse_problem<'a>(input: &'a str) -> IResult<&'a str, TSPLProblem> {
+    parse_header(input).and_then(|(input, header)| parse_data_section(input, header))
+}
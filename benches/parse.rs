@@ -0,0 +1,43 @@
+#[macro_use]
+extern crate nom;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::fs;
+use tsplib::*;
+
+//It is kept as it was, lints and all.
+#[allow(clippy::type_complexity, clippy::needless_lifetimes)]
+mod baseline;
+
+//Reading the file is as fast as parsing can get, so it's measured too.
+fn parse_large(c: &mut Criterion) {
+    for name in ["pla33810", "pla85900"].iter() {
+        let path = format!("examples/alltsp/problems/{}.tsp", name);
+        let contents = fs::read_to_string(&path).unwrap();
+        let mut group = c.benchmark_group(*name);
+        group.sample_size(10);
+        group.bench_function("read_to_string", |b| {
+            b.iter(|| fs::read_to_string(&path).unwrap())
+        });
+        group.bench_function("parse_str", |b| b.iter(|| parse_str(&contents).unwrap()));
+        //The old parser stops at the space after NODE_COORD_SECTION, so it gets the file
+        //without trailing spaces, and has to read all of it for the comparison to mean anything.
+        let trimmed: String = contents
+            .lines()
+            .map(|l| l.trim_end().to_string() + "\n")
+            .collect();
+        let (_, old) = baseline::parse_problem(&trimmed).unwrap();
+        assert_eq!(
+            old.data.node_coordinates,
+            parse_str(&contents).unwrap().data.node_coordinates
+        );
+        group.bench_function("baseline", |b| {
+            b.iter(|| baseline::parse_problem(&trimmed).unwrap())
+        });
+        group.bench_function("parse_path", |b| b.iter(|| parse_path(&path).unwrap()));
+        group.finish();
+    }
+}
+
+criterion_group!(benches, parse_large);
+criterion_main!(benches);
@@ -0,0 +1,260 @@
+use crate::enums::*;
+use crate::oracle::*;
+
+///How many times something was found, with the first few examples.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Findings<T> {
+    pub count: usize,
+    pub examples: Vec<T>,
+}
+
+impl<T> Findings<T> {
+    fn new() -> Self {
+        Findings {
+            count: 0,
+            examples: vec![],
+        }
+    }
+
+    fn add(&mut self, max_examples: usize, example: T) {
+        self.count += 1;
+        if self.examples.len() < max_examples {
+            self.examples.push(example);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+}
+
+///Going from `from` to `to` directly costs more than going through `via`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TriangleViolation {
+    pub from: usize,
+    pub via: usize,
+    pub to: usize,
+    pub direct: EdgeWeight,
+    pub through_via: u64,
+}
+
+///What the edge weights of a problem look like. Nodes are indexed from 0.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct WeightReport {
+    pub dimension: usize,
+    ///Whether the header says the problem is symmetric, which is true for every type except ATSP.
+    pub claimed_symmetric: bool,
+    ///Whether `dist(i, j) == dist(j, i)` for every pair of nodes.
+    pub symmetric: bool,
+    ///The `(i, j)` pairs with `i < j` and `dist(i, j) != dist(j, i)`.
+    pub asymmetric_pairs: Findings<(usize, usize)>,
+    ///The distinct weights found on the diagonal, smallest first.
+    pub diagonal_values: Vec<EdgeWeight>,
+    ///Edges between two different nodes that have a weight of 0.
+    ///For symmetric problems only the `(i, j)` with `i < j` are counted.
+    pub zero_weight_edges: Findings<(usize, usize)>,
+    ///The smallest weight considered a "big M", a sentinel that forbids an edge
+    ///(like the 9999 on the diagonal of br17).
+    pub big_m: Option<EdgeWeight>,
+    ///Edges between two different nodes that have a big M weight.
+    pub big_m_edges: Findings<(usize, usize)>,
+    ///Triples of nodes that break the triangle inequality.
+    ///Edges with a big M weight are left out, since they break it on purpose.
+    pub triangle_violations: Findings<TriangleViolation>,
+}
+
+///How far apart a weight has to be from all the smaller ones to be a big M.
+pub const BIG_M_GAP: EdgeWeight = 10;
+
+//How many of the largest distinct weights detect_big_m looks at.
+const BIG_M_CANDIDATES: usize = 4;
+
+//Looks for a sentinel value at the top of the weights: the smallest of the few largest
+//distinct weights that is at least BIG_M_GAP times larger than every weight under it.
+//A single pass that only keeps those few weights, so it doesn't cost more than reading them.
+fn detect_big_m<O: DistanceOracle>(oracle: &O) -> Option<EdgeWeight> {
+    let n = oracle.dimension();
+    //In increasing order.
+    let mut top: Vec<EdgeWeight> = Vec::with_capacity(BIG_M_CANDIDATES + 1);
+    for w in (0..n).flat_map(|i| (0..n).map(move |j| oracle.dist(i, j))) {
+        if let Err(pos) = top.binary_search(&w) {
+            if top.len() < BIG_M_CANDIDATES || pos > 0 {
+                top.insert(pos, w);
+                if top.len() > BIG_M_CANDIDATES {
+                    top.remove(0);
+                }
+            }
+        }
+    }
+    top.windows(2)
+        .rev()
+        .find(|w| w[0] > 0 && u64::from(w[1]) >= u64::from(w[0]) * u64::from(BIG_M_GAP))
+        .map(|w| w[1])
+}
+
+///Goes over every weight of `oracle` and reports on how well they behave like a metric.
+///`big_m` is the smallest weight to treat as a sentinel, when `None` it is detected
+///from a large gap at the top of the weights. At most `max_examples` examples are kept
+///for each kind of finding.
+///Checking the triangle inequality looks at every triple of nodes, so this takes O(n³).
+pub fn analyze_weights<O: DistanceOracle>(
+    oracle: &O,
+    claimed_symmetric: bool,
+    big_m: Option<EdgeWeight>,
+    max_examples: usize,
+) -> WeightReport {
+    let n = oracle.dimension();
+    let big_m = big_m.or_else(|| detect_big_m(oracle));
+    let is_big_m = |w: EdgeWeight| big_m.is_some_and(|m| w >= m);
+
+    let mut asymmetric_pairs = Findings::new();
+    for i in 0..n {
+        for j in (i + 1)..n {
+            if oracle.dist(i, j) != oracle.dist(j, i) {
+                asymmetric_pairs.add(max_examples, (i, j));
+            }
+        }
+    }
+    let symmetric = asymmetric_pairs.is_empty();
+
+    let mut diagonal_values: Vec<EdgeWeight> = (0..n).map(|i| oracle.dist(i, i)).collect();
+    diagonal_values.sort_unstable();
+    diagonal_values.dedup();
+
+    let mut zero_weight_edges = Findings::new();
+    let mut big_m_edges = Findings::new();
+    for i in 0..n {
+        for j in 0..n {
+            if i == j || (symmetric && j < i) {
+                continue;
+            }
+            let w = oracle.dist(i, j);
+            if w == 0 {
+                zero_weight_edges.add(max_examples, (i, j));
+            }
+            if is_big_m(w) {
+                big_m_edges.add(max_examples, (i, j));
+            }
+        }
+    }
+
+    let mut triangle_violations = Findings::new();
+    for from in 0..n {
+        for to in 0..n {
+            if from == to || (symmetric && to < from) {
+                continue;
+            }
+            let direct = oracle.dist(from, to);
+            if is_big_m(direct) {
+                continue;
+            }
+            for via in 0..n {
+                if via == from || via == to {
+                    continue;
+                }
+                let (a, b) = (oracle.dist(from, via), oracle.dist(via, to));
+                if is_big_m(a) || is_big_m(b) {
+                    continue;
+                }
+                let through_via = u64::from(a) + u64::from(b);
+                if u64::from(direct) > through_via {
+                    triangle_violations.add(
+                        max_examples,
+                        TriangleViolation {
+                            from,
+                            via,
+                            to,
+                            direct,
+                            through_via,
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    WeightReport {
+        dimension: n,
+        claimed_symmetric,
+        symmetric,
+        asymmetric_pairs,
+        diagonal_values,
+        zero_weight_edges,
+        big_m,
+        big_m_edges,
+        triangle_violations,
+    }
+}
+
+impl TSPLProblem {
+    ///Runs `analyze_weights` over the problem's distances, detecting the big M
+    ///and keeping up to 10 examples of each finding.
+    pub fn weight_report(&self) -> Result<WeightReport, OracleError> {
+        let claimed_symmetric = self.header.problem_type != ProblemType::ATSP;
+        Ok(analyze_weights(
+            &self.distance_oracle()?,
+            claimed_symmetric,
+            None,
+            10,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metric() {
+        let m = MatrixDistances::new(vec![vec![0, 1, 2], vec![1, 0, 1], vec![2, 1, 0]]);
+        let r = analyze_weights(&m, true, None, 10);
+        assert!(r.symmetric);
+        assert_eq!(r.diagonal_values, vec![0]);
+        assert!(r.zero_weight_edges.is_empty());
+        assert_eq!(r.big_m, None);
+        assert!(r.triangle_violations.is_empty());
+    }
+
+    #[test]
+    fn test_big_m_is_at_the_top() {
+        let row = |ws: &[EdgeWeight]| MatrixDistances::new(vec![ws.to_vec(); ws.len()]);
+        assert_eq!(detect_big_m(&row(&[0, 3, 4, 5, 500, 501])), Some(500));
+        //A gap under the largest few weights is not a sentinel, just spread out weights.
+        assert_eq!(detect_big_m(&row(&[1, 50, 51, 52, 53, 54])), None);
+    }
+
+    #[test]
+    fn test_findings() {
+        let m = MatrixDistances::new(vec![
+            vec![9999, 0, 5, 1],
+            vec![0, 9999, 1, 9999],
+            vec![5, 2, 9999, 1],
+            vec![1, 9999, 1, 9999],
+        ]);
+        let r = analyze_weights(&m, true, None, 1);
+        assert!(!r.symmetric);
+        assert_eq!(
+            r.asymmetric_pairs,
+            Findings {
+                count: 1,
+                examples: vec![(1, 2)]
+            }
+        );
+        assert_eq!(r.diagonal_values, vec![9999]);
+        assert_eq!(r.big_m, Some(9999));
+        assert_eq!(r.zero_weight_edges.count, 2);
+        assert_eq!(r.big_m_edges.count, 2);
+        //0 -> 2 costs 5 directly, but 1 through 1 and 2 through 3, and the other way around as well.
+        assert_eq!(r.triangle_violations.count, 4);
+        assert_eq!(
+            r.triangle_violations.examples,
+            vec![TriangleViolation {
+                from: 0,
+                via: 1,
+                to: 2,
+                direct: 5,
+                through_via: 1,
+            }]
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use crate::enums::*;
+use crate::oracle::*;
+use crate::transform::*;
+
+///An ATSP turned into a symmetric TSP on twice as many nodes, with the 2n-node
+///transformation of Jonker and Volgenant.
+///
+///Every node `i` of the ATSP gets a ghost node `i + n`. Going from node `i` to its ghost
+///is free, going from the ghost of `i` to node `j` costs the ATSP weight from `i` to `j`,
+///and all other edges are forbidden. A tour then has to alternate between nodes and
+///their ghosts, and reading it in the direction where each node comes right before its
+///ghost gives the ATSP tour.
+///
+///The original scheme gives the node to ghost edges a large negative weight, but
+///`EdgeWeight`s are unsigned, so everything is shifted up by `big_m` instead:
+///node to ghost edges cost 0, ghost to node edges cost the ATSP weight plus `big_m`, and
+///forbidden edges cost `2 * big_m`. `big_m` is larger than any ATSP tour, so an optimal
+///symmetric tour costs exactly `n * big_m` more than an optimal ATSP tour.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SymmetricTransform {
+    ///The symmetric problem, an `EXPLICIT` TSP with its weights in `UPPER_ROW` format.
+    pub problem: TSPLProblem,
+    pub big_m: EdgeWeight,
+    atsp_dimension: usize,
+}
+
+impl SymmetricTransform {
+    ///The number of nodes of the original ATSP.
+    pub fn atsp_dimension(&self) -> usize {
+        self.atsp_dimension
+    }
+
+    ///Turns the cost of a tour on the symmetric problem into the cost of the same tour on the ATSP.
+    pub fn atsp_cost(&self, symmetric_cost: u64) -> u64 {
+        symmetric_cost - self.atsp_dimension as u64 * u64::from(self.big_m)
+    }
+
+    ///Turns an ATSP tour into the matching tour on the symmetric problem,
+    ///by visiting each node's ghost right after it. Nodes are indexed from 0.
+    pub fn to_symmetric_tour(&self, atsp_tour: &[usize]) -> Tour {
+        let n = self.atsp_dimension;
+        atsp_tour.iter().flat_map(|&i| vec![i, i + n]).collect()
+    }
+
+    ///Turns a tour on the symmetric problem back into a tour on the ATSP, starting at node 0.
+    ///Nodes are indexed from 0.
+    pub fn to_atsp_tour(&self, tour: &[usize]) -> Result<Tour, TransformError> {
+        let n = self.atsp_dimension;
+        let len = 2 * n;
+        if tour.len() != len {
+            return Err(TransformError::WrongTourLength {
+                expected: len,
+                found: tour.len(),
+            });
+        }
+        if n == 0 {
+            return Ok(vec![]);
+        }
+        let start = tour
+            .iter()
+            .position(|&i| i == 0)
+            .ok_or(TransformError::NotATransformedTour { node: 0 })?;
+        //Walk the tour in the direction where the ghost of node 0 comes right after it.
+        let step = if tour[(start + 1) % len] == n {
+            1
+        } else if tour[(start + len - 1) % len] == n {
+            len - 1
+        } else {
+            return Err(TransformError::NotATransformedTour { node: 0 });
+        };
+        let mut seen = vec![false; n];
+        let mut res = Vec::with_capacity(n);
+        let mut pos = start;
+        for _ in 0..n {
+            let node = tour[pos];
+            let ghost = tour[(pos + step) % len];
+            if node >= n || ghost != node + n || seen[node] {
+                return Err(TransformError::NotATransformedTour { node: node % n });
+            }
+            seen[node] = true;
+            res.push(node);
+            pos = (pos + 2 * step) % len;
+        }
+        Ok(res)
+    }
+}
+
+impl TSPLProblem {
+    ///Transforms an asymmetric problem into a symmetric one on twice as many nodes,
+    ///see `SymmetricTransform`. The weights of nodes to themselves are ignored.
+    pub fn to_symmetric(&self) -> Result<SymmetricTransform, TransformError> {
+        let oracle = self.distance_oracle()?;
+        let n = oracle.dimension();
+        //Larger than any tour: the sum of the largest weight leaving each node.
+        let largest_out: u64 = (0..n)
+            .map(|i| {
+                (0..n)
+                    .filter(|&j| j != i)
+                    .map(|j| u64::from(oracle.dist(i, j)))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .sum();
+        let big_m = largest_out + 1;
+        let max_weight = (0..n)
+            .flat_map(|i| (0..n).filter(move |&j| j != i).map(move |j| (i, j)))
+            .map(|(i, j)| u64::from(oracle.dist(i, j)))
+            .max()
+            .unwrap_or(0);
+        if 2 * big_m > u64::from(EdgeWeight::MAX) || max_weight + big_m > u64::from(EdgeWeight::MAX)
+        {
+            return Err(TransformError::WeightOverflow);
+        }
+        let big_m = big_m as EdgeWeight;
+
+        //Nodes are 0..n and ghosts n..2n, and the weights are symmetric,
+        //so we only need to work out the upper triangle.
+        let weight = |a: usize, b: usize| -> EdgeWeight {
+            let (a, b) = if a <= b { (a, b) } else { (b, a) };
+            if a == b {
+                0
+            } else if b < n || a >= n {
+                2 * big_m
+            } else if b - n == a {
+                0
+            } else {
+                //a is a node and b the ghost of node b - n: the edge from b - n to a.
+                oracle.dist(b - n, a) + big_m
+            }
+        };
+        let weights = encode(2 * n, weight, &EdgeWeightFormat::UPPER_ROW)?;
+
+        let mut data = TSPLData::empty();
+        data.edge_weights = Some(weights);
+        let header = TSPLMeta {
+            problem_type: ProblemType::TSP,
+            dimension: 2 * n as u32,
+            edge_weight_type: EdgeWeightType::EXPLICIT,
+            edge_weight_format: Some(EdgeWeightFormat::UPPER_ROW),
+            edge_data_format: None,
+            node_coord_type: NodeCoordType::NO_COORDS,
+            display_data_type: DisplayDataType::NO_DISPLAY,
+            ..self.header.clone()
+        };
+        Ok(SymmetricTransform {
+            problem: TSPLProblem { header, data },
+            big_m,
+            atsp_dimension: n,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tour_cost<O: DistanceOracle>(oracle: &O, tour: &[usize]) -> u64 {
+        tour.iter()
+            .zip(tour.iter().cycle().skip(1))
+            .map(|(&a, &b)| u64::from(oracle.dist(a, b)))
+            .sum()
+    }
+
+    //Every tour on `nodes` that starts with the first one.
+    fn all_tours(nodes: &[usize]) -> Vec<Tour> {
+        if nodes.len() <= 1 {
+            return vec![nodes.to_vec()];
+        }
+        let mut res = vec![];
+        for i in 1..nodes.len() {
+            let mut rest = nodes.to_vec();
+            let next = rest.remove(i);
+            rest.remove(0);
+            for mut t in all_tours(&[vec![next], rest].concat()) {
+                t.insert(0, nodes[0]);
+                res.push(t);
+            }
+        }
+        res
+    }
+
+    fn small_atsp() -> TSPLProblem {
+        let mut data = TSPLData::empty();
+        data.edge_weights = Some(vec![
+            0, 1, 9, 4, //
+            7, 0, 2, 8, //
+            3, 6, 0, 1, //
+            2, 9, 5, 0,
+        ]);
+        TSPLProblem {
+            header: TSPLMeta {
+                name: "small".to_string(),
+                problem_type: ProblemType::ATSP,
+                comment: "".to_string(),
+                dimension: 4,
+                capacity: None,
+                edge_weight_type: EdgeWeightType::EXPLICIT,
+                edge_weight_format: Some(EdgeWeightFormat::FULL_MATRIX),
+                edge_data_format: None,
+                node_coord_type: NodeCoordType::NO_COORDS,
+                display_data_type: DisplayDataType::NO_DISPLAY,
+                extra: vec![],
+            },
+            data,
+        }
+    }
+
+    #[test]
+    fn test_optimum_is_preserved() {
+        let atsp = small_atsp();
+        let atsp_oracle = atsp.distance_oracle().unwrap();
+        let t = atsp.to_symmetric().unwrap();
+        let sym_oracle = t.problem.distance_oracle().unwrap();
+        assert!(sym_oracle.is_symmetric());
+
+        let (atsp_opt, atsp_best) = all_tours(&[0, 1, 2, 3])
+            .into_iter()
+            .map(|tour| (tour_cost(&atsp_oracle, &tour), tour))
+            .min()
+            .unwrap();
+        let (sym_opt, sym_best) = all_tours(&(0..8).collect::<Vec<_>>())
+            .into_iter()
+            .map(|tour| (tour_cost(&sym_oracle, &tour), tour))
+            .min()
+            .unwrap();
+        assert_eq!(t.atsp_cost(sym_opt), atsp_opt);
+        assert_eq!(t.to_atsp_tour(&sym_best), Ok(atsp_best));
+    }
+
+    #[test]
+    fn test_not_a_transformed_tour() {
+        let t = small_atsp().to_symmetric().unwrap();
+        assert_eq!(
+            t.to_atsp_tour(&[0, 4, 1, 5]),
+            Err(TransformError::WrongTourLength {
+                expected: 8,
+                found: 4
+            })
+        );
+        assert_eq!(
+            t.to_atsp_tour(&[0, 4, 1, 2, 5, 6, 3, 7]),
+            Err(TransformError::NotATransformedTour { node: 1 })
+        );
+        assert_eq!(
+            t.to_atsp_tour(&[0, 4, 0, 4, 2, 6, 3, 7]),
+            Err(TransformError::NotATransformedTour { node: 0 })
+        );
+    }
+}
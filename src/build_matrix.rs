@@ -1,56 +1,221 @@
-//use crate::enums::*;
-
-// fn build_distance_matrix(
-//     dimension: usize,
-//     ewf: &EdgeWeightFormat,
-//     edges: Vec<Vec<u32>>,
-// ) -> Option<EdgeWeightMatrix> {
-//     if *ewf == EdgeWeightFormat::FUNCTION {
-//         return None;
-//     }
-//     let mut res: EdgeWeightMatrix = vec![vec![std::u32::MAX; dimension]; dimension];
-//     let mut r: usize = 0;
-//     let mut c: usize = 0;
-
-//     let rc_range: Vec<(usize)> = match ewf {
-//         EdgeWeightFormat::FULL_MATRIX => (0..dimension).flat_map(|_r| 0..dimension).collect(),
-//         EdgeWeightFormat::LOWER_ROW => (1..dimension).flat_map(|r| 0..(r - 1)).collect(),
-//         EdgeWeightFormat::UPPER_COL => (1..dimension).flat_map(|r| 0..(r - 1)).collect(),
-//         EdgeWeightFormat::LOWER_DIAG_ROW => (0..dimension).flat_map(|r| 0..r).collect(),
-//         EdgeWeightFormat::UPPER_DIAG_COL => (0..dimension).flat_map(|r| 0..r).collect(),
-//         EdgeWeightFormat::UPPER_DIAG_ROW => (0..dimension).flat_map(|r| r..dimension).collect(),
-//         EdgeWeightFormat::LOWER_DIAG_COL => (0..dimension).flat_map(|r| r..dimension).collect(),
-//         EdgeWeightFormat::UPPER_ROW => (0..(dimension - 1))
-//             .flat_map(|r| (r + 1)..dimension)
-//             .collect(),
-//         EdgeWeightFormat::LOWER_COL => (0..(dimension - 1))
-//             .flat_map(|r| (r + 1)..dimension)
-//             .collect(),
-//     };
-
-//     for weight in edges.flatten() {
-//         (r, c) = rc_range.next();
-//         res[r][c] = weight;
-//     }
-// }
-
-// //The TSPLIB concrete edge weights are given as lists of numbers.
-// //This is the easiest one, but we need to collect all the vectors together first
-// fn build_full_matrix(
-//     dimension: usize,
-//     ewf: &EdgeWeightFormat,
-//     edges: Vec<Vec<u32>>,
-// ) -> Option<EdgeWeightMatrix> {
-//     if *ewf == EdgeWeightFormat::FUNCTION {
-//         return None;
-//     }
-//     let mut combined: Vec<u32> = Vec::<u32>::new();
-//     for line in edges.into_iter() {
-//         combined.extend(line);
-//     }
-
-//     match ewf {
-//         EdgeWeightFormat::FULL_MATRIX => Some(combined.chunks(dimension).collect()),
-//         _ => None,
-//     }
-// }
+use crate::enums::*;
+use std::error::Error;
+use std::fmt;
+
+///Why an `EDGE_WEIGHT_SECTION` could not be expanded into an `EdgeWeightMatrix`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum MatrixError {
+    ///The problem has no `EDGE_WEIGHT_SECTION`.
+    MissingEdgeWeights,
+    ///`EDGE_WEIGHT_FORMAT` is missing, or is `FUNCTION`, so the weights aren't a matrix.
+    NotAMatrix(Option<EdgeWeightFormat>),
+    ///The section holds a different number of weights than the format calls for at this dimension.
+    WrongEntryCount {
+        format: EdgeWeightFormat,
+        dimension: usize,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for MatrixError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MatrixError::MissingEdgeWeights => write!(f, "the problem has no EDGE_WEIGHT_SECTION"),
+            MatrixError::NotAMatrix(Some(ewf)) => {
+                write!(f, "EDGE_WEIGHT_FORMAT {} does not describe a matrix", ewf)
+            }
+            MatrixError::NotAMatrix(None) => write!(f, "the problem has no EDGE_WEIGHT_FORMAT"),
+            MatrixError::WrongEntryCount {
+                format,
+                dimension,
+                expected,
+                found,
+            } => write!(
+                f,
+                "a {} matrix of dimension {} has {} entries, but EDGE_WEIGHT_SECTION has {}",
+                format, dimension, expected, found
+            ),
+        }
+    }
+}
+
+impl Error for MatrixError {}
+
+///How many weights an `EDGE_WEIGHT_SECTION` in this format holds for `dimension` nodes.
+///Returns `None` for `FUNCTION`.
+pub fn entry_count(ewf: &EdgeWeightFormat, dimension: usize) -> Option<usize> {
+    let n = dimension;
+    match ewf {
+        EdgeWeightFormat::FUNCTION => None,
+        EdgeWeightFormat::FULL_MATRIX => Some(n * n),
+        EdgeWeightFormat::UPPER_ROW
+        | EdgeWeightFormat::LOWER_ROW
+        | EdgeWeightFormat::UPPER_COL
+        | EdgeWeightFormat::LOWER_COL => Some(n * n.saturating_sub(1) / 2),
+        EdgeWeightFormat::UPPER_DIAG_ROW
+        | EdgeWeightFormat::LOWER_DIAG_ROW
+        | EdgeWeightFormat::UPPER_DIAG_COL
+        | EdgeWeightFormat::LOWER_DIAG_COL => Some(n * (n + 1) / 2),
+    }
+}
+
+//The (row, column) position of every weight in the section, in the order they are written.
+//The *_COL formats walk the columns of a triangle, so we swap the pair to get (row, column).
+//All formats except FULL_MATRIX are symmetric, and the caller should mirror the position.
+pub(crate) fn positions(
+    ewf: &EdgeWeightFormat,
+    dimension: usize,
+) -> Option<Box<dyn Iterator<Item = (usize, usize)>>> {
+    let n = dimension;
+    let it: Box<dyn Iterator<Item = (usize, usize)>> = match ewf {
+        EdgeWeightFormat::FUNCTION => return None,
+        EdgeWeightFormat::FULL_MATRIX => {
+            Box::new((0..n).flat_map(move |r| (0..n).map(move |c| (r, c))))
+        }
+        EdgeWeightFormat::UPPER_ROW => {
+            Box::new((0..n).flat_map(move |r| ((r + 1)..n).map(move |c| (r, c))))
+        }
+        EdgeWeightFormat::LOWER_ROW => Box::new((0..n).flat_map(|r| (0..r).map(move |c| (r, c)))),
+        EdgeWeightFormat::UPPER_DIAG_ROW => {
+            Box::new((0..n).flat_map(move |r| (r..n).map(move |c| (r, c))))
+        }
+        EdgeWeightFormat::LOWER_DIAG_ROW => {
+            Box::new((0..n).flat_map(|r| (0..=r).map(move |c| (r, c))))
+        }
+        EdgeWeightFormat::UPPER_COL => Box::new((0..n).flat_map(|c| (0..c).map(move |r| (r, c)))),
+        EdgeWeightFormat::LOWER_COL => {
+            Box::new((0..n).flat_map(move |c| ((c + 1)..n).map(move |r| (r, c))))
+        }
+        EdgeWeightFormat::UPPER_DIAG_COL => {
+            Box::new((0..n).flat_map(|c| (0..=c).map(move |r| (r, c))))
+        }
+        EdgeWeightFormat::LOWER_DIAG_COL => {
+            Box::new((0..n).flat_map(move |c| (c..n).map(move |r| (r, c))))
+        }
+    };
+    Some(it)
+}
+
+///Expands the weights of an `EDGE_WEIGHT_SECTION` into a full `dimension` x `dimension` matrix.
+///For the triangular formats the other half is filled in symmetrically, and formats without
+///a diagonal get zeroes on it.
+pub fn build_distance_matrix(
+    dimension: usize,
+    ewf: &EdgeWeightFormat,
+    weights: &[EdgeWeight],
+) -> Result<EdgeWeightMatrix, MatrixError> {
+    let expected =
+        entry_count(ewf, dimension).ok_or_else(|| MatrixError::NotAMatrix(Some(ewf.clone())))?;
+    if weights.len() != expected {
+        return Err(MatrixError::WrongEntryCount {
+            format: ewf.clone(),
+            dimension,
+            expected,
+            found: weights.len(),
+        });
+    }
+    let mut res: EdgeWeightMatrix = vec![vec![0; dimension]; dimension];
+    let symmetric = *ewf != EdgeWeightFormat::FULL_MATRIX;
+    //positions() is only None for FUNCTION, which entry_count already turned away.
+    for ((r, c), &weight) in positions(ewf, dimension).unwrap().zip(weights) {
+        res[r][c] = weight;
+        if symmetric {
+            res[c][r] = weight;
+        }
+    }
+    Ok(res)
+}
+
+impl TSPLProblem {
+    ///Builds the full edge weight matrix of an explicit problem from its `EDGE_WEIGHT_SECTION`
+    ///and `EDGE_WEIGHT_FORMAT`. Rows and columns are indexed from 0.
+    pub fn edge_weight_matrix(&self) -> Result<EdgeWeightMatrix, MatrixError> {
+        let weights = self
+            .data
+            .edge_weights
+            .as_ref()
+            .ok_or(MatrixError::MissingEdgeWeights)?;
+        let ewf = self
+            .header
+            .edge_weight_format
+            .as_ref()
+            .ok_or(MatrixError::NotAMatrix(None))?;
+        build_distance_matrix(self.header.dimension as usize, ewf, weights)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //The symmetric matrix
+    // 0 1 2
+    // 1 0 3
+    // 2 3 0
+    //in every layout.
+    fn sample() -> EdgeWeightMatrix {
+        vec![vec![0, 1, 2], vec![1, 0, 3], vec![2, 3, 0]]
+    }
+
+    #[test]
+    fn test_all_layouts() {
+        let layouts = vec![
+            (
+                EdgeWeightFormat::FULL_MATRIX,
+                vec![0, 1, 2, 1, 0, 3, 2, 3, 0],
+            ),
+            (EdgeWeightFormat::UPPER_ROW, vec![1, 2, 3]),
+            (EdgeWeightFormat::LOWER_ROW, vec![1, 2, 3]),
+            (EdgeWeightFormat::UPPER_DIAG_ROW, vec![0, 1, 2, 0, 3, 0]),
+            (EdgeWeightFormat::LOWER_DIAG_ROW, vec![0, 1, 0, 2, 3, 0]),
+            (EdgeWeightFormat::UPPER_COL, vec![1, 2, 3]),
+            (EdgeWeightFormat::LOWER_COL, vec![1, 2, 3]),
+            (EdgeWeightFormat::UPPER_DIAG_COL, vec![0, 1, 0, 2, 3, 0]),
+            (EdgeWeightFormat::LOWER_DIAG_COL, vec![0, 1, 2, 0, 3, 0]),
+        ];
+        for (ewf, weights) in layouts {
+            assert_eq!(
+                build_distance_matrix(3, &ewf, &weights),
+                Ok(sample()),
+                "{}",
+                ewf
+            );
+        }
+    }
+
+    #[test]
+    fn test_column_layouts_are_transposed_rows() {
+        //Distinct weights, so that reading in the wrong order shows up.
+        let weights = vec![1, 2, 3, 4, 5, 6];
+        assert_eq!(
+            build_distance_matrix(4, &EdgeWeightFormat::UPPER_COL, &weights),
+            build_distance_matrix(4, &EdgeWeightFormat::LOWER_ROW, &weights)
+        );
+        assert_eq!(
+            build_distance_matrix(4, &EdgeWeightFormat::LOWER_COL, &weights),
+            build_distance_matrix(4, &EdgeWeightFormat::UPPER_ROW, &weights)
+        );
+        assert_ne!(
+            build_distance_matrix(4, &EdgeWeightFormat::UPPER_ROW, &weights),
+            build_distance_matrix(4, &EdgeWeightFormat::LOWER_ROW, &weights)
+        );
+    }
+
+    #[test]
+    fn test_wrong_entry_count() {
+        assert_eq!(
+            build_distance_matrix(3, &EdgeWeightFormat::UPPER_ROW, &[1, 2]),
+            Err(MatrixError::WrongEntryCount {
+                format: EdgeWeightFormat::UPPER_ROW,
+                dimension: 3,
+                expected: 3,
+                found: 2,
+            })
+        );
+        assert_eq!(
+            build_distance_matrix(3, &EdgeWeightFormat::FUNCTION, &[]),
+            Err(MatrixError::NotAMatrix(Some(EdgeWeightFormat::FUNCTION)))
+        );
+    }
+}
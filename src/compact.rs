@@ -0,0 +1,247 @@
+use crate::enums::*;
+use crate::oracle::*;
+use std::thread;
+
+//The cells are stored as u16 when every weight fits, and as u32 otherwise.
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Cells {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+///A precomputed distance matrix that takes as little memory as it can.
+///
+///Symmetric problems only store the lower triangle (diagonal included), which is
+///`n(n+1)/2` cells instead of `n²`, and the cells are `u16`s when the largest weight
+///fits in one, `u32`s otherwise. For pr2392 that is 5.7MB instead of the 23MB of an
+///`EdgeWeightMatrix`.
+///
+///Compared to computing distances on the fly with `CoordDistances`, a lookup is a
+///multiplication and a memory read instead of a square root (or a handful of
+///trigonometric functions for `GEO`), which pays off for solvers that ask for the
+///same distances many times. The price is the memory, which still grows with `n²`:
+///the triangle of d18512 has 171 million cells, and the one of pla85900 3.7 billion,
+///so the largest instances are better served by `CoordDistances` and candidate lists.
+///Building the matrix computes every distance twice, once to pick the cell width and once
+///to fill the cells, spread over several threads.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CompactMatrix {
+    dimension: usize,
+    symmetric: bool,
+    cells: Cells,
+}
+
+//Where row r of the lower triangle starts.
+fn triangle_start(r: usize) -> usize {
+    r * (r + 1) / 2
+}
+
+//The length of row r of the matrix, in an n by n problem.
+type RowLen = fn(usize, usize) -> usize;
+
+//Splits the rows into one run per thread, each with about the same number of cells.
+//Gives the first row of each run, one past its last row, and its number of cells.
+fn runs(n: usize, size: usize, threads: usize, row_len: RowLen) -> Vec<(usize, usize, usize)> {
+    let per_thread = size / threads.max(1) + 1;
+    let mut runs = vec![];
+    let mut row = 0;
+    while row < n {
+        let first_row = row;
+        let mut len = 0;
+        while row < n && len < per_thread {
+            len += row_len(row, n);
+            row += 1;
+        }
+        runs.push((first_row, row, len));
+    }
+    runs
+}
+
+//The largest weight of the matrix, without keeping any of them.
+fn max_weight<O: DistanceOracle + Sync>(
+    oracle: &O,
+    runs: &[(usize, usize, usize)],
+    row_len: RowLen,
+) -> EdgeWeight {
+    let n = oracle.dimension();
+    thread::scope(|s| {
+        let handles: Vec<_> = runs
+            .iter()
+            .map(|&(first_row, end_row, _)| {
+                s.spawn(move || {
+                    (first_row..end_row)
+                        .flat_map(|r| (0..row_len(r, n)).map(move |c| (r, c)))
+                        .map(|(r, c)| oracle.dist(r, c))
+                        .max()
+                        .unwrap_or(0)
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .max()
+            .unwrap_or(0)
+    })
+}
+
+//Fills the cells row by row, a run per thread, converting the weights with `cell`.
+fn fill<T, O>(
+    oracle: &O,
+    runs: &[(usize, usize, usize)],
+    size: usize,
+    row_len: RowLen,
+    cell: fn(EdgeWeight) -> T,
+) -> Vec<T>
+where
+    T: Copy + Default + Send,
+    O: DistanceOracle + Sync,
+{
+    let n = oracle.dimension();
+    let mut cells = vec![T::default(); size];
+    let mut rest: &mut [T] = &mut cells;
+    thread::scope(|s| {
+        for &(first_row, _, len) in runs {
+            let (run, tail) = std::mem::take(&mut rest).split_at_mut(len);
+            rest = tail;
+            s.spawn(move || {
+                let mut r = first_row;
+                let mut c = 0;
+                for slot in run.iter_mut() {
+                    *slot = cell(oracle.dist(r, c));
+                    c += 1;
+                    if c == row_len(r, n) {
+                        r += 1;
+                        c = 0;
+                    }
+                }
+            });
+        }
+    });
+    cells
+}
+
+impl CompactMatrix {
+    ///Asks `oracle` for every distance, using up to `threads` threads.
+    ///Symmetric oracles are only asked for the lower triangle.
+    ///The largest distance is found first, so that only the final cells are ever allocated.
+    pub fn from_oracle<O: DistanceOracle + Sync>(oracle: &O, threads: usize) -> Self {
+        let n = oracle.dimension();
+        let symmetric = oracle.is_symmetric();
+        let (size, row_len): (usize, RowLen) = if symmetric {
+            (triangle_start(n), |r, _| r + 1)
+        } else {
+            (n * n, |_, n| n)
+        };
+        let runs = runs(n, size, threads, row_len);
+        let cells = if max_weight(oracle, &runs, row_len) <= EdgeWeight::from(u16::MAX) {
+            Cells::U16(fill(oracle, &runs, size, row_len, |w| w as u16))
+        } else {
+            Cells::U32(fill(oracle, &runs, size, row_len, |w| w))
+        };
+        CompactMatrix {
+            dimension: n,
+            symmetric,
+            cells,
+        }
+    }
+
+    ///Computes the distances between all of `coords` under `ewt`, using up to `threads` threads.
+    pub fn from_coords(
+        ewt: EdgeWeightType,
+        coords: &[Coord],
+        threads: usize,
+    ) -> Result<Self, OracleError> {
+        Ok(Self::from_oracle(
+            &CoordDistances::new(ewt, coords)?,
+            threads,
+        ))
+    }
+
+    ///How many bytes a cell takes, 2 or 4.
+    pub fn cell_width(&self) -> usize {
+        match self.cells {
+            Cells::U16(_) => 2,
+            Cells::U32(_) => 4,
+        }
+    }
+
+    ///How many bytes the cells take.
+    pub fn memory_bytes(&self) -> usize {
+        let len = match &self.cells {
+            Cells::U16(c) => c.len(),
+            Cells::U32(c) => c.len(),
+        };
+        len * self.cell_width()
+    }
+
+    fn index(&self, i: usize, j: usize) -> usize {
+        if self.symmetric {
+            let (r, c) = if i >= j { (i, j) } else { (j, i) };
+            triangle_start(r) + c
+        } else {
+            i * self.dimension + j
+        }
+    }
+}
+
+impl DistanceOracle for CompactMatrix {
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+    fn dist(&self, i: usize, j: usize) -> EdgeWeight {
+        assert!(i < self.dimension && j < self.dimension);
+        let idx = self.index(i, j);
+        match &self.cells {
+            Cells::U16(c) => EdgeWeight::from(c[idx]),
+            Cells::U32(c) => c[idx],
+        }
+    }
+    fn is_symmetric(&self) -> bool {
+        self.symmetric
+    }
+}
+
+impl TSPLProblem {
+    ///Precomputes every distance of the problem into a `CompactMatrix`.
+    ///Coordinate based problems are computed with up to `threads` threads.
+    pub fn compact_matrix(&self, threads: usize) -> Result<CompactMatrix, OracleError> {
+        Ok(CompactMatrix::from_oracle(
+            &self.distance_oracle()?,
+            threads,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_width() {
+        let small = MatrixDistances::new(vec![vec![0, 1], vec![1, 0]]);
+        let m = CompactMatrix::from_oracle(&small, 1);
+        assert_eq!(m.cell_width(), 2);
+        assert_eq!(m.memory_bytes(), 6);
+
+        let big = MatrixDistances::new(vec![vec![0, 70000], vec![70000, 0]]);
+        let m = CompactMatrix::from_oracle(&big, 1);
+        assert_eq!(m.cell_width(), 4);
+        assert_eq!(m.dist(0, 1), 70000);
+    }
+
+    #[test]
+    fn test_asymmetric() {
+        let weights = vec![vec![9, 1, 2], vec![3, 9, 4], vec![5, 6, 9]];
+        let asym = MatrixDistances::new(weights.clone());
+        for threads in 1..5 {
+            let m = CompactMatrix::from_oracle(&asym, threads);
+            assert!(!m.is_symmetric());
+            for (i, row) in weights.iter().enumerate() {
+                for (j, &w) in row.iter().enumerate() {
+                    assert_eq!(m.dist(i, j), w);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use crate::enums::*;
+
+//The distance functions from section 2 of the TSPLIB spec. They all work on
+//doubles and round to an integer at the end, so they return `EdgeWeight`s.
+
+//The spec's nint(), "rounds to the nearest integer".
+fn nint(x: f64) -> EdgeWeight {
+    (x + 0.5) as EdgeWeight
+}
+
+pub(crate) fn xyz(c: &Coord) -> (f64, f64, Option<f64>) {
+    match c {
+        Coord::Coord2(_, x, y) => (x.raw(), y.raw(), None),
+        Coord::Coord3(_, x, y, z) => (x.raw(), y.raw(), Some(z.raw())),
+    }
+}
+
+//Per coordinate differences, the third one is only there if both nodes are 3d.
+fn deltas(a: &Coord, b: &Coord) -> (f64, f64, Option<f64>) {
+    let (xa, ya, za) = xyz(a);
+    let (xb, yb, zb) = xyz(b);
+    (xa - xb, ya - yb, za.and_then(|za| zb.map(|zb| za - zb)))
+}
+
+///Euclidean distance, rounded to the nearest integer.
+pub fn euc_2d(a: &Coord, b: &Coord) -> EdgeWeight {
+    let (xd, yd, _) = deltas(a, b);
+    nint((xd * xd + yd * yd).sqrt())
+}
+
+///Euclidean distance in 3 dimensions, rounded to the nearest integer.
+pub fn euc_3d(a: &Coord, b: &Coord) -> Option<EdgeWeight> {
+    let (xd, yd, zd) = deltas(a, b);
+    zd.map(|zd| nint((xd * xd + yd * yd + zd * zd).sqrt()))
+}
+
+///Euclidean distance, rounded up to the next integer.
+pub fn ceil_2d(a: &Coord, b: &Coord) -> EdgeWeight {
+    let (xd, yd, _) = deltas(a, b);
+    (xd * xd + yd * yd).sqrt().ceil() as EdgeWeight
+}
+
+///Manhattan distance, rounded to the nearest integer.
+pub fn man_2d(a: &Coord, b: &Coord) -> EdgeWeight {
+    let (xd, yd, _) = deltas(a, b);
+    nint(xd.abs() + yd.abs())
+}
+
+///Manhattan distance in 3 dimensions, rounded to the nearest integer.
+pub fn man_3d(a: &Coord, b: &Coord) -> Option<EdgeWeight> {
+    let (xd, yd, zd) = deltas(a, b);
+    zd.map(|zd| nint(xd.abs() + yd.abs() + zd.abs()))
+}
+
+///Maximum distance, the largest of the rounded coordinate differences.
+pub fn max_2d(a: &Coord, b: &Coord) -> EdgeWeight {
+    let (xd, yd, _) = deltas(a, b);
+    nint(xd.abs()).max(nint(yd.abs()))
+}
+
+///Maximum distance in 3 dimensions.
+pub fn max_3d(a: &Coord, b: &Coord) -> Option<EdgeWeight> {
+    let (xd, yd, zd) = deltas(a, b);
+    zd.map(|zd| nint(xd.abs()).max(nint(yd.abs())).max(nint(zd.abs())))
+}
+
+//The spec wants this exact value of PI, using std::f64::consts::PI gives
+//slightly different distances.
+#[allow(clippy::approx_constant)]
+const GEO_PI: f64 = 3.141592;
+//Radius of the idealized sphere the GEO distances are computed on, in km.
+pub(crate) const GEO_RRR: f64 = 6378.388;
+
+//Converts a DDD.MM (degrees and minutes) coordinate into radians.
+//The degrees are truncated, like the reference implementation does with `(int) x`.
+pub(crate) fn geo_radians(x: f64) -> f64 {
+    let deg = x.trunc();
+    let min = x - deg;
+    GEO_PI * (deg + 5.0 * min / 3.0) / 180.0
+}
+
+///Geographical distance in km, where x is the latitude and y the longitude,
+///both given in DDD.MM format.
+///Like the TSPLIB reference code, this rounds up to 1 for two points in the same place,
+///so `TSPLProblem::distance` and the oracles leave it out for a node and itself.
+pub fn geo(a: &Coord, b: &Coord) -> EdgeWeight {
+    let (xa, ya, _) = xyz(a);
+    let (xb, yb, _) = xyz(b);
+    let (lat_a, lon_a) = (geo_radians(xa), geo_radians(ya));
+    let (lat_b, lon_b) = (geo_radians(xb), geo_radians(yb));
+    let q1 = (lon_a - lon_b).cos();
+    let q2 = (lat_a - lat_b).cos();
+    let q3 = (lat_a + lat_b).cos();
+    (GEO_RRR * (0.5 * ((1.0 + q1) * q2 - (1.0 - q1) * q3)).acos() + 1.0) as EdgeWeight
+}
+
+///Pseudo-Euclidean distance, used by att48 and att532.
+pub fn att(a: &Coord, b: &Coord) -> EdgeWeight {
+    let (xd, yd, _) = deltas(a, b);
+    let r = ((xd * xd + yd * yd) / 10.0).sqrt();
+    let t = nint(r);
+    if f64::from(t) < r {
+        t + 1
+    } else {
+        t
+    }
+}
+
+//The crystallography problems have the three angles of a diffractometer as coordinates:
+//phi (x, which wraps around at 360 degrees), chi (y) and two theta (z).
+//The distance is the time it takes the slowest of the three motors to reach the next
+//position, given the speed of each motor in degrees per time unit, scaled by 100.
+fn xray(a: &Coord, b: &Coord, speeds: (f64, f64, f64)) -> Option<EdgeWeight> {
+    let (phi, chi, two_theta) = deltas(a, b);
+    let phi = phi.abs().min(360.0 - phi.abs());
+    two_theta.map(|two_theta| {
+        let cost = (phi / speeds.0)
+            .max(chi.abs() / speeds.1)
+            .max(two_theta.abs() / speeds.2);
+        nint(100.0 * cost)
+    })
+}
+
+///Crystallography distance for the first diffractometer, where all motors move at the same speed.
+pub fn xray1(a: &Coord, b: &Coord) -> Option<EdgeWeight> {
+    xray(a, b, (1.0, 1.0, 1.0))
+}
+
+///Crystallography distance for the second diffractometer, which has faster motors
+///(1.25, 1.5 and 1.15 times the speed of the first).
+pub fn xray2(a: &Coord, b: &Coord) -> Option<EdgeWeight> {
+    xray(a, b, (1.25, 1.5, 1.15))
+}
+
+///Computes the distance between two coordinates for a coordinate based `EdgeWeightType`.
+///Returns `None` for `EXPLICIT` and `SPECIAL`, which have no distance function,
+///and for the 3d metrics (including `XRAY1` and `XRAY2`) when one of the coordinates is 2d.
+pub fn coord_distance(ewt: &EdgeWeightType, a: &Coord, b: &Coord) -> Option<EdgeWeight> {
+    match ewt {
+        EdgeWeightType::EUC_2D => Some(euc_2d(a, b)),
+        EdgeWeightType::EUC_3D => euc_3d(a, b),
+        EdgeWeightType::MAX_2D => Some(max_2d(a, b)),
+        EdgeWeightType::MAX_3D => max_3d(a, b),
+        EdgeWeightType::MAN_2D => Some(man_2d(a, b)),
+        EdgeWeightType::MAN_3D => man_3d(a, b),
+        EdgeWeightType::CEIL_2D => Some(ceil_2d(a, b)),
+        EdgeWeightType::GEO => Some(geo(a, b)),
+        EdgeWeightType::ATT => Some(att(a, b)),
+        EdgeWeightType::XRAY1 => xray1(a, b),
+        EdgeWeightType::XRAY2 => xray2(a, b),
+        EdgeWeightType::EXPLICIT | EdgeWeightType::SPECIAL => None,
+    }
+}
+
+impl TSPLProblem {
+    ///Distance between the nodes at (0-based) indices `i` and `j` of `node_coordinates`,
+    ///using the problem's `edge_weight_type`.
+    ///Returns `None` if the problem has no coordinates, if `i` or `j` are out of range,
+    ///or if the edge weight type is not computed from coordinates.
+    ///A node is at distance 0 from itself, even for `GEO`, see `geo`.
+    pub fn distance(&self, i: usize, j: usize) -> Option<EdgeWeight> {
+        let coords = self.data.node_coordinates.as_ref()?;
+        let d = coord_distance(
+            &self.header.edge_weight_type,
+            coords.get(i)?,
+            coords.get(j)?,
+        )?;
+        Some(if i == j { 0 } else { d })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use noisy_float::prelude::*;
+
+    fn c2(x: f64, y: f64) -> Coord {
+        Coord::Coord2(1, n64(x), n64(y))
+    }
+    fn c3(x: f64, y: f64, z: f64) -> Coord {
+        Coord::Coord3(1, n64(x), n64(y), n64(z))
+    }
+
+    #[test]
+    fn test_2d_metrics() {
+        let (a, b) = (c2(0.0, 0.0), c2(3.0, 4.4));
+        assert_eq!(euc_2d(&a, &b), 5);
+        assert_eq!(ceil_2d(&a, &b), 6);
+        assert_eq!(man_2d(&a, &b), 7);
+        assert_eq!(max_2d(&a, &b), 4);
+    }
+
+    #[test]
+    fn test_3d_metrics() {
+        let (a, b) = (c3(0.0, 0.0, 0.0), c3(1.0, 2.0, 2.0));
+        assert_eq!(euc_3d(&a, &b), Some(3));
+        assert_eq!(man_3d(&a, &b), Some(5));
+        assert_eq!(max_3d(&a, &b), Some(2));
+        assert_eq!(euc_3d(&a, &c2(1.0, 2.0)), None);
+    }
+
+    #[test]
+    fn test_att_rounds_up() {
+        //sqrt(100 / 10) = 3.16, which nint rounds down, so ATT bumps it to 4.
+        assert_eq!(att(&c2(0.0, 0.0), &c2(10.0, 0.0)), 4);
+        assert_eq!(att(&c2(0.0, 0.0), &c2(0.0, 0.0)), 0);
+    }
+
+    #[test]
+    fn test_geo_diagonal() {
+        let a = c2(16.47, 96.10);
+        assert_eq!(geo(&a, &a), 1);
+        let p = crate::parse_str(
+            "TYPE: TSP\nDIMENSION: 2\nEDGE_WEIGHT_TYPE: GEO\nNODE_COORD_SECTION\n\
+             1 16.47 96.10\n2 16.47 94.44\nEOF\n",
+        )
+        .unwrap();
+        assert_eq!(p.distance(0, 0), Some(0));
+        assert_eq!(p.distance(0, 1), Some(153));
+        let oracle = p.distance_oracle().unwrap();
+        assert_eq!(oracle.dist(1, 1), 0);
+        assert_eq!(oracle.dist(1, 0), 153);
+    }
+
+    #[test]
+    fn test_xray() {
+        let (a, b) = (c3(10.0, 5.0, 2.0), c3(350.0, 8.0, 4.3));
+        //phi wraps around, so the phi motor only travels 20 degrees.
+        assert_eq!(xray1(&a, &b), Some(2000));
+        assert_eq!(xray2(&a, &b), Some(1600));
+        assert_eq!(coord_distance(&EdgeWeightType::XRAY2, &a, &b), Some(1600));
+        //When phi barely moves, the two theta motor is the slowest on the second machine.
+        let c = c3(10.5, 5.0, 4.3);
+        assert_eq!(xray1(&a, &c), Some(230));
+        assert_eq!(xray2(&a, &c), Some(200));
+        assert_eq!(xray1(&a, &c2(10.0, 5.0)), None);
+    }
+
+    #[test]
+    fn test_no_distance_function() {
+        let (a, b) = (c2(0.0, 0.0), c2(3.0, 4.0));
+        assert_eq!(coord_distance(&EdgeWeightType::EXPLICIT, &a, &b), None);
+        assert_eq!(coord_distance(&EdgeWeightType::SPECIAL, &a, &b), None);
+    }
+}
@@ -7,6 +7,7 @@ use std::fmt::Debug;
 //the problem body based on the metadata in the header:
 
 ///A TSPLIB Instance
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct TSPLProblem {
     pub header: TSPLMeta,
@@ -14,6 +15,7 @@ pub struct TSPLProblem {
 }
 
 ///Header information for the problem instance
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct TSPLMeta {
     ///Identifies the data file.
@@ -33,12 +35,29 @@ pub struct TSPLMeta {
     ///Describes the format in which the edges of a graph are given, if the graph is not complete.
     pub edge_data_format: Option<EdgeDataFormat>,
     ///Specifies whether coordinates are associated with each node (which, for example may be used for either graphical display or distance computations).
+    ///When the header doesn't say, it is whatever the `NODE_COORD_SECTION` has.
     pub node_coord_type: NodeCoordType,
     ///Specifies how a graphical display of the nodes can be obtained.
+    ///When the header doesn't say, it is worked out from the sections, like the spec does.
     pub display_data_type: DisplayDataType,
+    ///Header keywords that aren't in the spec, like the `VEHICLES` or `SERVICE_TIME` of the
+    ///LKH and CVRPLIB extensions, with their values, in the order of the file. The keywords
+    ///are in capitals, however the file wrote them.
+    pub extra: Vec<(String, String)>,
+}
+
+impl TSPLMeta {
+    ///The value of a keyword that isn't in the spec, if the header has it. `key` is in capitals.
+    pub fn extra_value(&self, key: &str) -> Option<&str> {
+        self.extra
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
 }
 
 ///Problem instance data
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct TSPLData {
     pub node_coordinates: Option<Vec<Coord>>,
@@ -65,6 +84,7 @@ impl TSPLData {
         }
     }
 }
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 ///Holds a pair or triple of floats.
 pub enum Coord {
@@ -73,6 +93,7 @@ pub enum Coord {
 }
 
 ///Holds a CVRP Demand for a node
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Demand(pub usize, pub u32);
 
@@ -86,6 +107,8 @@ pub type EdgeList = Vec<Edge>;
 pub type EdgeWeight = u32;
 ///`Vec<u32>`
 pub type EdgeWeightList = Vec<EdgeWeight>;
+///`Vec<Vec<u32>>`, a full matrix of edge weights indexed by `[row][column]`
+pub type EdgeWeightMatrix = Vec<EdgeWeightList>;
 ///`Vec<usize>`
 pub type Adj = Vec<usize>;
 
@@ -93,6 +116,7 @@ pub type Adj = Vec<usize>;
 /// The adjacency list version is a vec of `dimension` elements, each of which is a list of
 /// connections. Non-connected nodes are still counted as empty lists.
 /// TSPLData holds a vec of this type.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum EdgeData {
     Edge(Edge),
@@ -100,6 +124,7 @@ pub enum EdgeData {
 }
 
 #[cfg_attr(test, derive(EnumIter))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, PartialEq, Eq, Clone, Display, EnumString)]
 /// Specifies the type of the problem.
 pub enum ProblemType {
@@ -118,6 +143,7 @@ pub enum ProblemType {
 }
 
 #[cfg_attr(test, derive(EnumIter))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, PartialEq, Eq, Clone, Display, EnumString)]
 /// Specifies how the edge weights (or distances) are given.
 pub enum EdgeWeightType {
@@ -136,7 +162,21 @@ pub enum EdgeWeightType {
     SPECIAL,
 }
 
+impl EdgeWeightType {
+    ///The coordinates this metric works on, or None for `EXPLICIT` and `SPECIAL`, which don't
+    ///say.
+    pub fn node_coord_type(&self) -> Option<NodeCoordType> {
+        use EdgeWeightType::*;
+        match self {
+            EUC_2D | MAX_2D | MAN_2D | CEIL_2D | GEO | ATT => Some(NodeCoordType::TWOD_COORDS),
+            EUC_3D | MAX_3D | MAN_3D | XRAY1 | XRAY2 => Some(NodeCoordType::THREED_COORDS),
+            EXPLICIT | SPECIAL => None,
+        }
+    }
+}
+
 #[cfg_attr(test, derive(EnumIter))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, PartialEq, Eq, Clone, Display, EnumString)]
 /// Describes the format of the edge weights if they are given explicitly.
 pub enum EdgeWeightFormat {
@@ -153,6 +193,7 @@ pub enum EdgeWeightFormat {
 }
 
 #[cfg_attr(test, derive(EnumIter))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, PartialEq, Eq, Clone, Display, EnumString)]
 ///Describes the format in which the edges of a graph are given, if the graph is not complete.
 pub enum EdgeDataFormat {
@@ -161,6 +202,7 @@ pub enum EdgeDataFormat {
 }
 
 #[cfg_attr(test, derive(EnumIter))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, PartialEq, Eq, Clone, Display, EnumString)]
 ///Specifies whether coordinates are associated with each node (which, for example may be used for either graphical display or distance computations).
 pub enum NodeCoordType {
@@ -171,8 +213,15 @@ pub enum NodeCoordType {
 
 ///Specifies how a graphical display of the nodes can be obtained.
 #[cfg_attr(test, derive(EnumIter))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, PartialEq, Eq, Clone, Display, EnumString)]
 pub enum DisplayDataType {
+    ///The spec spells it `COORD_DISPLAY`, which is also how it is written out.
+    #[strum(to_string = "COORD_DISPLAY", serialize = "COORDS_DISPLAY")]
+    #[cfg_attr(
+        feature = "serde",
+        serde(rename = "COORD_DISPLAY", alias = "COORDS_DISPLAY")
+    )]
     COORDS_DISPLAY,
     TWOD_DISPLAY,
     NO_DISPLAY,
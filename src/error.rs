@@ -0,0 +1,228 @@
+use crate::enums::{DisplayDataType, NodeCoordType};
+use crate::validate::ValidationError;
+use std::error::Error;
+use std::fmt;
+
+///What went wrong while parsing a TSPLIB file.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum TsplibErrorKind {
+    ///The file could not be read.
+    Io(String),
+    ///A header keyword that is not made of capital letters, digits and underscores.
+    UnknownKeyword(String),
+    ///A header keyword (other than `COMMENT`) or a section shows up twice.
+    Duplicate(String),
+    ///A header line is not a `KEY : VALUE` pair.
+    NotAKeyValue,
+    ///The value of a header keyword could not be parsed.
+    InvalidValue(String),
+    ///A keyword that every file needs, `TYPE` or `DIMENSION`, is missing.
+    MissingKeyword(String),
+    ///Something in a data section that is not a number.
+    InvalidNumber(String),
+    ///A number in a data section that should be an integer: an id, a demand or a weight.
+    ///Those can't be fractional or negative, and have to fit in 32 bits.
+    NotAnInteger(String),
+    ///A row of a data section has the wrong number of values for that section.
+    WrongRowLength(usize),
+    ///A row of numbers outside of any data section.
+    DataOutsideSection,
+    ///A header line after the data sections have started.
+    HeaderAfterData(String),
+    ///Strict parsing turns every diagnostic into an error.
+    Strict(DiagnosticKind),
+    ///The file parsed, but its sections don't match each other or the header.
+    ///The error is located at the title of the section the first one is in.
+    Invalid(Vec<ValidationError>),
+}
+
+impl fmt::Display for TsplibErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TsplibErrorKind::Io(e) => write!(f, "could not read the file: {}", e),
+            TsplibErrorKind::UnknownKeyword(key) => write!(f, "unknown keyword {}", key),
+            TsplibErrorKind::Duplicate(key) => write!(f, "{} is given more than once", key),
+            TsplibErrorKind::NotAKeyValue => write!(f, "expected a KEY : VALUE line"),
+            TsplibErrorKind::InvalidValue(value) => write!(f, "invalid value {:?}", value),
+            TsplibErrorKind::MissingKeyword(key) => write!(f, "the header has no {}", key),
+            TsplibErrorKind::InvalidNumber(found) => {
+                write!(f, "expected a number, found {:?}", found)
+            }
+            TsplibErrorKind::NotAnInteger(found) => {
+                write!(f, "expected a non-negative integer, found {:?}", found)
+            }
+            TsplibErrorKind::WrongRowLength(len) => {
+                write!(f, "a row of this section can't have {} values", len)
+            }
+            TsplibErrorKind::DataOutsideSection => write!(f, "data outside of any section"),
+            TsplibErrorKind::HeaderAfterData(key) => {
+                write!(f, "header keyword {} after the data sections", key)
+            }
+            TsplibErrorKind::Strict(kind) => kind.fmt(f),
+            TsplibErrorKind::Invalid(errors) => {
+                let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "{}", errors.join("; "))
+            }
+        }
+    }
+}
+
+///An error while parsing a TSPLIB file, and where it happened.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TsplibError {
+    ///The file being parsed, when parsing from a path.
+    pub path: Option<String>,
+    ///The line of the error, counting from 1. 0 when the error is not on a given line,
+    ///eg when the file could not be read.
+    pub line: usize,
+    ///The column of the error on its line, counting from 1.
+    pub column: usize,
+    ///The header keyword or the section being parsed, eg `DIMENSION` or `NODE_COORD_SECTION`.
+    pub context: Option<String>,
+    pub kind: TsplibErrorKind,
+}
+
+impl TsplibError {
+    pub(crate) fn new(line: usize, column: usize, kind: TsplibErrorKind) -> Self {
+        TsplibError {
+            path: None,
+            line,
+            column,
+            context: None,
+            kind,
+        }
+    }
+
+    pub(crate) fn context(mut self, context: &str) -> Self {
+        self.context = Some(context.to_string());
+        self
+    }
+
+    ///A human readable description of the error, without its location.
+    pub fn message(&self) -> String {
+        self.kind.to_string()
+    }
+}
+
+//Writes `path:line:column: in CONTEXT: `, leaving out what isn't known.
+fn write_location(
+    f: &mut fmt::Formatter,
+    path: Option<&str>,
+    line: usize,
+    column: usize,
+    context: Option<&str>,
+) -> fmt::Result {
+    if let Some(path) = path {
+        write!(f, "{}:", path)?;
+    }
+    if line > 0 {
+        write!(f, "{}:{}:", line, column)?;
+    }
+    if path.is_some() || line > 0 {
+        write!(f, " ")?;
+    }
+    if let Some(context) = context {
+        write!(f, "in {}: ", context)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for TsplibError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_location(
+            f,
+            self.path.as_deref(),
+            self.line,
+            self.column,
+            self.context.as_deref(),
+        )?;
+        self.kind.fmt(f)
+    }
+}
+
+impl Error for TsplibError {}
+
+///Something odd the parser worked around instead of failing.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum DiagnosticKind {
+    ///A row of `EDGE_DATA_SECTION` was skipped, because there is no `EDGE_DATA_FORMAT`
+    ///to say how to read it.
+    SkippedRow,
+    ///A section this parser doesn't know, its rows are skipped.
+    UnknownSection(String),
+    ///A header keyword that the spec doesn't give a default for was missing, so `value` was used.
+    DefaultedField { key: String, value: String },
+    ///There is more than blank lines after `EOF`, which is ignored.
+    TextAfterEof,
+    ///A tour that the section or the file ended before its -1. It is kept anyway.
+    UnterminatedTour,
+    ///`NODE_COORD_TYPE` doesn't go with the `NODE_COORD_SECTION`, whose `data` is used.
+    NodeCoordTypeMismatch {
+        header: NodeCoordType,
+        data: NodeCoordType,
+    },
+    ///`DISPLAY_DATA_TYPE` doesn't go with the sections the file has, so `data` is used.
+    DisplayDataTypeMismatch {
+        header: DisplayDataType,
+        data: DisplayDataType,
+    },
+}
+
+impl fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiagnosticKind::SkippedRow => {
+                write!(f, "row skipped because there is no EDGE_DATA_FORMAT")
+            }
+            DiagnosticKind::UnknownSection(section) => {
+                write!(f, "unknown section {} is skipped", section)
+            }
+            DiagnosticKind::DefaultedField { key, value } => {
+                write!(f, "there is no {}, using {:?}", key, value)
+            }
+            DiagnosticKind::TextAfterEof => write!(f, "everything after EOF is ignored"),
+            DiagnosticKind::UnterminatedTour => write!(f, "a tour doesn't end with -1"),
+            DiagnosticKind::NodeCoordTypeMismatch { header, data } => write!(
+                f,
+                "NODE_COORD_TYPE is {}, but the data sections go with {}, which is used",
+                header, data
+            ),
+            DiagnosticKind::DisplayDataTypeMismatch { header, data } => write!(
+                f,
+                "DISPLAY_DATA_TYPE is {}, but the data sections go with {}, which is used",
+                header, data
+            ),
+        }
+    }
+}
+
+///A problem in a file that didn't stop it from parsing, and where it is.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Diagnostic {
+    ///The line, counting from 1.
+    pub line: usize,
+    ///The column on the line, counting from 1.
+    pub column: usize,
+    ///The header keyword or the section being parsed.
+    pub context: Option<String>,
+    pub kind: DiagnosticKind,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_location(f, None, self.line, self.column, self.context.as_deref())?;
+        self.kind.fmt(f)
+    }
+}
+
+impl From<Diagnostic> for TsplibError {
+    fn from(d: Diagnostic) -> Self {
+        TsplibError {
+            path: None,
+            line: d.line,
+            column: d.column,
+            context: d.context,
+            kind: TsplibErrorKind::Strict(d.kind),
+        }
+    }
+}
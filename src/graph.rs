@@ -0,0 +1,252 @@
+use crate::enums::*;
+use crate::oracle::*;
+use std::error::Error;
+use std::fmt;
+
+///Why the `EDGE_DATA_SECTION` of a problem could not be made into a `SparseGraph`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum GraphError {
+    ///The problem has no `EDGE_DATA_SECTION`.
+    MissingEdgeData,
+    ///An edge names a node that is not between 1 and `dimension`.
+    NodeOutOfRange { node: usize, dimension: usize },
+    ///An edge passed to `SparseGraph::from_edges` has an index that is not below `dimension`.
+    IndexOutOfRange { index: usize, dimension: usize },
+    ///The weights of the edges could not be looked up.
+    Oracle(OracleError),
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GraphError::MissingEdgeData => write!(f, "the problem has no EDGE_DATA_SECTION"),
+            GraphError::NodeOutOfRange { node, dimension } => write!(
+                f,
+                "an edge goes to node {}, but the nodes go from 1 to {}",
+                node, dimension
+            ),
+            GraphError::IndexOutOfRange { index, dimension } => write!(
+                f,
+                "an edge goes to index {}, but there are only {} nodes",
+                index, dimension
+            ),
+            GraphError::Oracle(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for GraphError {}
+
+impl From<OracleError> for GraphError {
+    fn from(e: OracleError) -> Self {
+        GraphError::Oracle(e)
+    }
+}
+
+///A weighted graph that only holds some of the edges between its nodes, stored as
+///compressed sparse rows: the neighbors of node `v` are
+///`targets[offsets[v]..offsets[v + 1]]`, sorted, with their weights alongside.
+///
+///Undirected graphs hold every edge in both of its rows.
+///Nodes are indexed from 0.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SparseGraph {
+    directed: bool,
+    offsets: Vec<usize>,
+    targets: Vec<usize>,
+    weights: Vec<EdgeWeight>,
+}
+
+impl SparseGraph {
+    ///Builds a graph on the nodes of `oracle` from a list of `(from, to)` edges,
+    ///looking up each edge's weight in `oracle`. Repeated edges are only kept once.
+    ///Nodes are indexed from 0.
+    pub fn from_edges<O: DistanceOracle>(
+        oracle: &O,
+        edges: &[Edge],
+        directed: bool,
+    ) -> Result<Self, GraphError> {
+        let n = oracle.dimension();
+        let mut arcs = Vec::with_capacity(if directed { 1 } else { 2 } * edges.len());
+        for &(from, to) in edges {
+            if let Some(&index) = [from, to].iter().find(|&&v| v >= n) {
+                return Err(GraphError::IndexOutOfRange {
+                    index,
+                    dimension: n,
+                });
+            }
+            arcs.push((from, to));
+            if !directed && from != to {
+                arcs.push((to, from));
+            }
+        }
+        arcs.sort_unstable();
+        arcs.dedup();
+
+        let mut offsets = vec![0; n + 1];
+        for &(from, _) in &arcs {
+            offsets[from + 1] += 1;
+        }
+        for v in 0..n {
+            offsets[v + 1] += offsets[v];
+        }
+        Ok(SparseGraph {
+            directed,
+            offsets,
+            weights: arcs.iter().map(|&(a, b)| oracle.dist(a, b)).collect(),
+            targets: arcs.into_iter().map(|(_, b)| b).collect(),
+        })
+    }
+
+    ///Builds a graph from the contents of an `EDGE_DATA_SECTION`, in either format.
+    ///Unlike the rest of this type, the nodes in `edge_data` count from 1, like in the file.
+    pub fn from_edge_data<O: DistanceOracle>(
+        oracle: &O,
+        edge_data: &[EdgeData],
+        directed: bool,
+    ) -> Result<Self, GraphError> {
+        let n = oracle.dimension();
+        let to_index = |node: usize| {
+            node.checked_sub(1)
+                .filter(|&v| v < n)
+                .ok_or(GraphError::NodeOutOfRange { node, dimension: n })
+        };
+        let mut edges = vec![];
+        for (row, data) in edge_data.iter().enumerate() {
+            match data {
+                EdgeData::Edge((from, to)) => edges.push((to_index(*from)?, to_index(*to)?)),
+                //Row `i` of a parsed ADJ_LIST holds the neighbors of node `i + 1`.
+                EdgeData::Adj(adj) => {
+                    let from = to_index(row + 1)?;
+                    for &to in adj {
+                        edges.push((from, to_index(to)?));
+                    }
+                }
+            }
+        }
+        Self::from_edges(oracle, &edges, directed)
+    }
+
+    ///The number of nodes.
+    pub fn dimension(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    pub fn is_directed(&self) -> bool {
+        self.directed
+    }
+
+    ///The number of edges, counting the edges of an undirected graph once.
+    pub fn edge_count(&self) -> usize {
+        self.edges().count()
+    }
+
+    ///How many edges leave `v`.
+    pub fn degree(&self, v: usize) -> usize {
+        self.offsets[v + 1] - self.offsets[v]
+    }
+
+    ///The nodes that an edge from `v` goes to, with the weight of that edge.
+    pub fn neighbors(&self, v: usize) -> impl Iterator<Item = (usize, EdgeWeight)> + '_ {
+        let range = self.offsets[v]..self.offsets[v + 1];
+        self.targets[range.clone()]
+            .iter()
+            .copied()
+            .zip(self.weights[range].iter().copied())
+    }
+
+    ///Every `(from, to, weight)` edge of the graph. The edges of an undirected graph
+    ///are only given once, with `from <= to`.
+    pub fn edges(&self) -> impl Iterator<Item = (usize, usize, EdgeWeight)> + '_ {
+        (0..self.dimension()).flat_map(move |v| {
+            self.neighbors(v)
+                .filter(move |&(w, _)| self.directed || v <= w)
+                .map(move |(w, weight)| (v, w, weight))
+        })
+    }
+}
+
+impl TSPLProblem {
+    ///Builds a `SparseGraph` out of the `EDGE_DATA_SECTION`, with the weights of the
+    ///edges coming from the problem's `EDGE_WEIGHT_TYPE`.
+    ///The graph is directed for ATSP problems, and undirected otherwise.
+    pub fn sparse_graph(&self) -> Result<SparseGraph, GraphError> {
+        let edge_data = self
+            .data
+            .edges
+            .as_ref()
+            .ok_or(GraphError::MissingEdgeData)?;
+        let directed = self.header.problem_type == ProblemType::ATSP;
+        SparseGraph::from_edge_data(&self.distance_oracle()?, edge_data, directed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weights() -> MatrixDistances {
+        MatrixDistances::new(vec![
+            vec![0, 1, 2, 3],
+            vec![4, 0, 5, 6],
+            vec![7, 8, 0, 9],
+            vec![10, 11, 12, 0],
+        ])
+    }
+
+    #[test]
+    fn test_undirected() {
+        let g = SparseGraph::from_edges(&weights(), &[(0, 1), (2, 0), (1, 0)], false).unwrap();
+        assert_eq!(g.edge_count(), 2);
+        assert_eq!(g.neighbors(0).collect::<Vec<_>>(), vec![(1, 1), (2, 2)]);
+        assert_eq!(g.neighbors(2).collect::<Vec<_>>(), vec![(0, 7)]);
+        assert_eq!(g.degree(3), 0);
+        assert_eq!(g.edges().collect::<Vec<_>>(), vec![(0, 1, 1), (0, 2, 2)]);
+    }
+
+    #[test]
+    fn test_directed() {
+        let g = SparseGraph::from_edges(&weights(), &[(0, 1), (2, 0), (1, 0)], true).unwrap();
+        assert_eq!(
+            g.edges().collect::<Vec<_>>(),
+            vec![(0, 1, 1), (1, 0, 4), (2, 0, 7)]
+        );
+        assert_eq!(g.degree(0), 1);
+    }
+
+    #[test]
+    fn test_index_out_of_range() {
+        assert_eq!(
+            SparseGraph::from_edges(&weights(), &[(0, 1), (3, 4)], false),
+            Err(GraphError::IndexOutOfRange {
+                index: 4,
+                dimension: 4
+            })
+        );
+    }
+
+    #[test]
+    fn test_edge_data_formats_agree() {
+        let list = vec![
+            EdgeData::Edge((1, 2)),
+            EdgeData::Edge((1, 4)),
+            EdgeData::Edge((3, 2)),
+        ];
+        let adj = vec![
+            EdgeData::Adj(vec![2, 4]),
+            EdgeData::Adj(vec![]),
+            EdgeData::Adj(vec![2]),
+            EdgeData::Adj(vec![]),
+        ];
+        let from_list = SparseGraph::from_edge_data(&weights(), &list, false).unwrap();
+        let from_adj = SparseGraph::from_edge_data(&weights(), &adj, false).unwrap();
+        assert_eq!(from_list, from_adj);
+        assert_eq!(
+            SparseGraph::from_edge_data(&weights(), &[EdgeData::Edge((5, 1))], false),
+            Err(GraphError::NodeOutOfRange {
+                node: 5,
+                dimension: 4
+            })
+        );
+    }
+}
@@ -4,132 +4,183 @@ extern crate strum_macros;
 extern crate nom;
 
 use noisy_float::prelude::*;
-use nom::character::complete::{
-    alphanumeric1, line_ending, multispace1, not_line_ending, space0, space1,
-};
-use nom::number::complete::double;
+use nom::character::complete::space1;
+use nom::number::complete::recognize_float;
 use nom::{Err, IResult};
-use std::fs;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
 use std::str::FromStr;
 #[allow(unused_imports)]
 use strum::IntoEnumIterator;
 
 mod enums;
 pub use self::enums::*;
+mod error;
+pub use self::error::*;
 mod build_matrix;
+pub use self::build_matrix::*;
+pub mod distance;
+mod oracle;
+pub use self::oracle::*;
+mod compact;
+pub use self::compact::*;
+mod transform;
+pub use self::transform::*;
+mod analysis;
+pub use self::analysis::*;
+mod atsp;
+pub use self::atsp::*;
+mod graph;
+pub use self::graph::*;
+mod neighbors;
+pub use self::neighbors::*;
+mod validate;
+pub use self::validate::*;
+mod solution;
+pub use self::solution::*;
+mod writer;
+pub use self::writer::*;
+mod reader;
 
-//Gives us a parser called kv() that takes a key to look for, and will return
-//the value in  a string of "KEY: VALUE"
-named_args!(kv<'a>(key: &'a str)<&'a str, &'a str>,
-   do_parse!(
-        tag!(key) >>
-        space0 >>
-        tag!(":") >>
-        space0 >>
-        value: not_line_ending >>
-        line_ending >>
-        (value.trim_end())
-    )
-);
-
-//Calls kv and then uses str::parse::<T> to parse the VALUE to the type you want
-fn kv_parse<'a, T>(input: &'a str, key: &'a str) -> IResult<&'a str, T>
-where
-    T: std::str::FromStr,
-{
-    kv(input, key).and_then(|(i, v)| match str::parse::<T>(v) {
-        Ok(tv) => Ok((i, tv)),
-        Err(_a) => Err(Err::Error((i, nom::error::ErrorKind::ParseTo))),
-    })
+//Splits a `KEY: VALUE` line at its first colon, and trims the key and the value.
+fn key_value(line: &str) -> Option<(&str, &str)> {
+    let colon = line.find(':')?;
+    Some((line[..colon].trim(), line[colon + 1..].trim()))
 }
 
-//This version of kv! looks for the first word to the right of the colon and calls
-//that the value. Needed for an odd case where someone wrote TYPE: TSP (Foo Bar)
-//and we want to treat that as TYPE: TSP.
-named_args!(kv_single<'a>(key: &'a str)<&'a str, &'a str>,
-   do_parse!(
-        tag!(key) >>
-        space0 >>
-        tag!(":") >>
-        space0 >>
-        value: alphanumeric1 >>
-        _ignore: not_line_ending >>
-        opt!(line_ending) >>
-        (value.trim_end())
-    )
-);
-//Copy of kv_parse for kv_single
-fn kv_parse_single<'a, T>(input: &'a str, key: &'a str) -> IResult<&'a str, T>
-where
-    T: std::str::FromStr,
-{
-    kv_single(input, key).and_then(|(i, v)| match str::parse::<T>(v) {
-        Ok(tv) => Ok((i, tv)),
-        Err(_a) => Err(Err::Error((i, nom::error::ErrorKind::ParseTo))),
-    })
+//The first word of a value, for the keys that only look at that. Needed for an odd case
+//where someone wrote TYPE: TSP (Foo Bar) and we want to treat that as TYPE: TSP.
+fn first_word(value: &str) -> &str {
+    value
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .next()
+        .unwrap_or("")
 }
 
 //TSPLIB defines all of these key:value pairs in its header, and they can be set
-//in any order.
-//Cool note: the way we map! the result of permutation! lets kv_parse! figure out
-//the wanted return type, and then it calls the right string -> Data type
-//conversion automatically.
+//in any order, so we collect them as they come and only build the TSPLMeta once
+//the first data section (or the end of the file) shows up.
 //The reason that COMMENT is different is that 1 of the problems has multiple
 //COMMENT lines as one big multiline comment, and I wanted to support that.
-//It doesn't need the call to complete! because many1 can take Incomplete, but
-//it looks weird to have many1() + ?. I tried many0 but hit some macro issues.
-fn parse_header(input: &str) -> IResult<&str, TSPLMeta> {
-    map!(
-        input,
-        complete!(permutation!(
-            complete!(call!(kv_parse, "NAME"))?,
-            complete!(call!(kv_parse_single, "TYPE")),
-            many1!(call!(kv_parse, "COMMENT"))?,
-            complete!(call!(kv_parse_single, "DIMENSION")),
-            complete!(call!(kv_parse, "EDGE_WEIGHT_TYPE"))?,
-            complete!(call!(kv_parse_single, "CAPACITY"))?,
-            complete!(call!(kv_parse, "EDGE_WEIGHT_FORMAT"))?,
-            complete!(call!(kv_parse, "EDGE_DATA_FORMAT"))?,
-            complete!(call!(kv_parse, "DISPLAY_DATA_TYPE"))?,
-            complete!(call!(kv_parse, "NODE_COORD_TYPE"))?
-        )),
-        |(
-            name,
-            problem_type,
-            comments,
-            dimension,
-            ewt,
-            capacity,
-            edge_weight_format,
-            edge_data_format,
-            ddt,
-            nct,
-        ): (
-            Option<String>,
-            ProblemType,
-            Option<Vec<String>>,
-            u32,
-            Option<EdgeWeightType>,
-            Option<u32>,
-            Option<EdgeWeightFormat>,
-            Option<EdgeDataFormat>,
-            Option<DisplayDataType>,
-            Option<NodeCoordType>,
-        )| TSPLMeta {
-            name: name.unwrap_or_else(|| "".to_string()),
+#[derive(Default)]
+struct HeaderFields {
+    name: Option<String>,
+    problem_type: Option<ProblemType>,
+    comments: Vec<String>,
+    dimension: Option<u32>,
+    capacity: Option<u32>,
+    edge_weight_type: Option<EdgeWeightType>,
+    edge_weight_format: Option<EdgeWeightFormat>,
+    edge_data_format: Option<EdgeDataFormat>,
+    display_data_type: Option<DisplayDataType>,
+    node_coord_type: Option<NodeCoordType>,
+    extra: Vec<(String, String)>,
+}
+
+//Parses the value of `key` into the field it goes in, unless the key was already set.
+fn set_once<T: FromStr>(
+    slot: &mut Option<T>,
+    key: &str,
+    value: &str,
+    parsed: &str,
+) -> Result<(), TsplibErrorKind> {
+    if slot.is_some() {
+        return Err(TsplibErrorKind::Duplicate(key.to_string()));
+    }
+    let parsed = parsed
+        .parse()
+        .map_err(|_| TsplibErrorKind::InvalidValue(value.to_string()))?;
+    *slot = Some(parsed);
+    Ok(())
+}
+
+impl HeaderFields {
+    //Reads one `KEY : VALUE` line, which has been trimmed and starts at `column`.
+    //The value is parsed with FromStr into the type of the field it goes in.
+    fn parse_line(&mut self, line: &str, number: usize, column: usize) -> Result<(), TsplibError> {
+        let (written_key, value) = key_value(line)
+            .ok_or_else(|| TsplibError::new(number, column, TsplibErrorKind::NotAKeyValue))?;
+        let value_column = column + line.len() - value.len();
+        //Keywords and the values of the enums can come in any case, `name : x` or `TYPE: tsp`.
+        let key = &written_key.to_ascii_uppercase()[..];
+        let upper = &value.to_ascii_uppercase()[..];
+        let res = match key {
+            "NAME" => set_once(&mut self.name, key, value, value),
+            "TYPE" => set_once(&mut self.problem_type, key, value, first_word(upper)),
+            "COMMENT" => {
+                self.comments.push(value.to_string());
+                Ok(())
+            }
+            "DIMENSION" => set_once(&mut self.dimension, key, value, first_word(value)),
+            "CAPACITY" => set_once(&mut self.capacity, key, value, first_word(value)),
+            "EDGE_WEIGHT_TYPE" => set_once(&mut self.edge_weight_type, key, value, upper),
+            "EDGE_WEIGHT_FORMAT" => set_once(&mut self.edge_weight_format, key, value, upper),
+            "EDGE_DATA_FORMAT" => set_once(&mut self.edge_data_format, key, value, upper),
+            "DISPLAY_DATA_TYPE" => set_once(&mut self.display_data_type, key, value, upper),
+            "NODE_COORD_TYPE" => set_once(&mut self.node_coord_type, key, value, upper),
+            _ if !is_keyword(key) => {
+                return Err(TsplibError::new(
+                    number,
+                    column,
+                    TsplibErrorKind::UnknownKeyword(written_key.to_string()),
+                ))
+            }
+            //Extensions of the format add their own keywords, we keep them in capitals like the others.
+            _ if self.extra.iter().any(|(k, _)| k == key) => {
+                Err(TsplibErrorKind::Duplicate(key.to_string()))
+            }
+            _ => {
+                self.extra.push((key.to_string(), value.to_string()));
+                Ok(())
+            }
+        };
+        res.map_err(|kind| {
+            let column = match kind {
+                TsplibErrorKind::InvalidValue(_) => value_column,
+                _ => column,
+            };
+            TsplibError::new(number, column, kind).context(key)
+        })
+    }
+
+    //TYPE and DIMENSION have to be there, everything else has a default.
+    //A TOUR file can do without DIMENSION, the parser gets it from the tour.
+    //`number` is the line the header ended on.
+    fn finish(self, number: usize) -> Result<TSPLMeta, TsplibError> {
+        let missing = |key: &str| {
+            TsplibError::new(number, 1, TsplibErrorKind::MissingKeyword(key.to_string()))
+        };
+        let problem_type = self.problem_type.ok_or_else(|| missing("TYPE"))?;
+        let dimension = match (self.dimension, &problem_type) {
+            (Some(dimension), _) => dimension,
+            (None, ProblemType::TOUR) => 0,
+            (None, _) => return Err(missing("DIMENSION")),
+        };
+        Ok(TSPLMeta {
+            name: self.name.unwrap_or_default(),
             problem_type,
-            comment: comments
-                .map(|comments| comments.join(""))
-                .unwrap_or_else(|| "".to_string()),
+            comment: self.comments.join(""),
             dimension,
-            capacity,
-            edge_weight_type: ewt.unwrap_or(EdgeWeightType::EUC_2D),
-            edge_data_format,
-            edge_weight_format,
-            node_coord_type: nct.unwrap_or(NodeCoordType::NO_COORDS),
-            display_data_type: ddt.unwrap_or(DisplayDataType::NO_DISPLAY),
-        }
-    )
+            capacity: self.capacity,
+            edge_weight_type: self.edge_weight_type.unwrap_or(EdgeWeightType::EUC_2D),
+            edge_data_format: self.edge_data_format,
+            edge_weight_format: self.edge_weight_format,
+            node_coord_type: self.node_coord_type.unwrap_or(NodeCoordType::NO_COORDS),
+            display_data_type: self
+                .display_data_type
+                .unwrap_or(DisplayDataType::NO_DISPLAY),
+            extra: self.extra,
+        })
+    }
+}
+
+//Keywords are made of capital letters, digits and underscores, like NODE_COORD_TYPE.
+fn is_keyword(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
 }
 #[test]
 fn test_parse_header() {
@@ -150,8 +201,9 @@ EDGE_WEIGHT_TYPE: EUC_2D
         edge_data_format: None,
         edge_weight_format: None,
         node_coord_type: NodeCoordType::NO_COORDS,
+        extra: vec![],
     };
-    assert_eq!(parse_header(header), Ok((" ", parsed)))
+    assert_eq!(parse_str(header).map(|p| p.header), Ok(parsed))
 }
 #[test]
 fn test_parse_header_works_with_missing_data() {
@@ -171,19 +223,22 @@ EDGE_WEIGHT_TYPE: EUC_2D
         edge_data_format: None,
         edge_weight_format: None,
         node_coord_type: NodeCoordType::NO_COORDS,
+        extra: vec![],
     };
-    assert_eq!(parse_header(header), Ok((" ", parsed)))
+    assert_eq!(parse_str(header).map(|p| p.header), Ok(parsed))
 }
 
-fn numbers_on_line(input: &str) -> IResult<&str, Vec<f64>> {
-    separated_list!(input, space1, double)
+//Finds the numbers on a line, keeping each of them as it is written, so that the line
+//parsers can read integers as integers and reals as reals.
+fn numbers_on_line(input: &str) -> IResult<&str, Vec<&str>> {
+    separated_list!(input, space1, recognize_float)
 }
 
 #[test]
 fn test_numbers_on_line() {
     assert_eq!(
-        numbers_on_line("1.2 2 3 4"),
-        Ok(("", vec![1.2, 2.0, 3.0, 4.0]))
+        numbers_on_line("1.2 2 3 -4"),
+        Ok(("", vec!["1.2", "2", "3", "-4"]))
     );
 }
 
@@ -199,128 +254,155 @@ EDGE_WEIGHT_DATA
 1 3 4 5 0 18 3
 5 7 1 9 3 8 2
 ...
-So we're abstracting that into a parser that looks for a section title, a newline,
-and then a bunch of lines that contain whitespace delimited numbers.
-We're going to unfortunately (but not wrongly) cast them to floats at first,
-And then call the given line_parser function on each line, which will turn lines of numbers
-into the right data we want.
+So each section is a title on its own line, and then a bunch of lines that contain
+whitespace delimited numbers, up to the next title or EOF. A line with just -1 on it
+ends a list, so it is skipped.
+We find the numbers on each line first, and then call the section's line parser on them,
+which reads each number as the integer or real the spec says it is, and turns lines of
+numbers into the right data we want.
     */
-fn get_section<'a, T>(
-    input: &'a str,
-    section_title: &'a str,
-    line_parser: fn(Vec<f64>) -> Option<T>,
-) -> IResult<&'a str, Vec<T>> {
-    do_parse!(
-        input,
-        tag!(section_title)
-            >> line_ending
-            >> space0
-            >> payload: separated_list!(multispace1, map_opt!(numbers_on_line, line_parser))
-            >> space0
-            >> opt!(line_ending)
-            >> opt!(complete!(tag!("-1")))
-            >> opt!(complete!(tag!("EOF\n")))
-            >> (payload)
-    )
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Display, EnumString)]
+#[allow(non_camel_case_types)]
+enum Section {
+    NODE_COORD_SECTION,
+    DEPOT_SECTION,
+    DEMAND_SECTION,
+    EDGE_DATA_SECTION,
+    FIXED_EDGES_SECTION,
+    DISPLAY_DATA_SECTION,
+    TOUR_SECTION,
+    EDGE_WEIGHT_SECTION,
+}
+
+//Why a line parser couldn't read a row of numbers.
+#[derive(Debug, PartialEq)]
+enum RowError<'a> {
+    //The row has this many numbers, which is wrong for the section.
+    Length(usize),
+    //A number that should be an integer of the right type, but isn't.
+    NotAnInteger(&'a str),
+    //A number that doesn't fit in a double.
+    NotANumber(&'a str),
+}
+
+type RowResult<'a, T> = Result<T, RowError<'a>>;
+
+//Ids, demands, edges and weights are integers. They're read straight from the text, so
+//nothing gets rounded, and anything fractional, negative or too big for T is an error.
+fn integer<T: FromStr>(token: &str) -> RowResult<'_, T> {
+    token.parse().map_err(|_| RowError::NotAnInteger(token))
+}
+
+//Coordinates are reals.
+fn real(token: &str) -> RowResult<'_, N64> {
+    match token.parse::<f64>() {
+        Ok(x) if x.is_finite() => Ok(n64(x)),
+        _ => Err(RowError::NotANumber(token)),
+    }
+}
+
+//The lists of TOUR_SECTION and of ADJ_LIST edges end with a -1, which can be on the
+//same line as the last of them. Some files end EDGE_WEIGHT_SECTION that way too.
+fn without_terminator<'a, 'b>(input: &'b [&'a str]) -> &'b [&'a str] {
+    match input.split_last() {
+        Some((&"-1", rest)) => rest,
+        _ => input,
+    }
 }
 
 //These functions parse individual lines of numbers into different domain-level types
 
-fn parse_depot_vec(input: Vec<f64>) -> Option<usize> {
-    match input.len() {
-        1 => Some(input[0] as usize),
-        _ => None,
+fn parse_depot_vec<'a>(input: &[&'a str]) -> RowResult<'a, usize> {
+    match *input {
+        [id] => integer(id),
+        _ => Err(RowError::Length(input.len())),
     }
 }
-fn parse_coord2_vec(input: Vec<f64>) -> Option<Coord> {
-    match input.len() {
-        3 => Some(Coord::Coord2(input[0] as i64, n64(input[1]), n64(input[2]))),
-        _ => None,
+fn parse_coord2_vec<'a>(input: &[&'a str]) -> RowResult<'a, Coord> {
+    match *input {
+        [id, x, y] => Ok(Coord::Coord2(integer(id)?, real(x)?, real(y)?)),
+        _ => Err(RowError::Length(input.len())),
     }
 }
 #[test]
 fn test_2d_coords() {
     let input = "1 1.0 3.0";
-    let input_vec = vec![1.0, 1.0, 3.0];
+    let input_vec = vec!["1", "1.0", "3.0"];
     assert_eq!(numbers_on_line(input), Ok(("", input_vec.clone())));
     assert_eq!(
-        parse_coord2_vec(input_vec),
-        Some(Coord::Coord2(1, n64(1.0), n64(3.0)))
+        parse_coord2_vec(&input_vec),
+        Ok(Coord::Coord2(1, n64(1.0), n64(3.0)))
+    );
+    assert_eq!(
+        parse_coord2_vec(&["1.5", "1.0", "3.0"]),
+        Err(RowError::NotAnInteger("1.5"))
     );
 }
 
-fn parse_coord3_vec(input: Vec<f64>) -> Option<Coord> {
-    match input.len() {
-        4 => Some(Coord::Coord3(
-            input[0] as i64,
-            n64(input[1]),
-            n64(input[2]),
-            n64(input[3]),
-        )),
-        _ => None,
+fn parse_coord3_vec<'a>(input: &[&'a str]) -> RowResult<'a, Coord> {
+    match *input {
+        [id, x, y, z] => Ok(Coord::Coord3(integer(id)?, real(x)?, real(y)?, real(z)?)),
+        _ => Err(RowError::Length(input.len())),
     }
 }
 
-fn parse_demand_vec(input: Vec<f64>) -> Option<Demand> {
-    match input.len() {
-        2 => Some(Demand(input[0] as usize, input[1] as u32)),
-        _ => None,
+fn parse_demand_vec<'a>(input: &[&'a str]) -> RowResult<'a, Demand> {
+    match *input {
+        [id, demand] => Ok(Demand(integer(id)?, integer(demand)?)),
+        _ => Err(RowError::Length(input.len())),
     }
 }
 
-fn parse_edge_vec(input: Vec<f64>) -> Option<Edge> {
-    match input.len() {
-        2 => Some((input[0] as usize, input[1] as usize)),
-        _ => None,
+fn parse_edge_vec<'a>(input: &[&'a str]) -> RowResult<'a, Edge> {
+    match *input {
+        [from, to] => Ok((integer(from)?, integer(to)?)),
+        _ => Err(RowError::Length(input.len())),
     }
 }
 
-fn parse_tour_vec(input: Vec<f64>) -> Option<Tour> {
-    match input.len() {
-        0 => None,
-        _ => Some(
-            input
-                .into_iter()
-                .map(|i| i as usize)
-                .collect::<Vec<usize>>(),
-        ),
+fn parse_weights_vec<'a>(input: &[&'a str]) -> RowResult<'a, EdgeWeightList> {
+    match input {
+        [] => Err(RowError::Length(0)),
+        weights => weights.iter().map(|&weight| integer(weight)).collect(),
     }
 }
-
-fn parse_weights_vec(input: Vec<f64>) -> Option<EdgeWeightList> {
-    match input.len() {
-        0 => None,
-        _ => Some(
-            input
-                .into_iter()
-                .map(|i| i as EdgeWeight)
-                .collect::<EdgeWeightList>(),
-        ),
-    }
+#[test]
+fn test_integers_stay_exact() {
+    assert_eq!(
+        parse_weights_vec(&["4294967295", "0"]),
+        Ok(vec![u32::MAX, 0])
+    );
+    assert_eq!(
+        parse_weights_vec(&["4294967296"]),
+        Err(RowError::NotAnInteger("4294967296"))
+    );
+    assert_eq!(
+        parse_weights_vec(&["3", "-2"]),
+        Err(RowError::NotAnInteger("-2"))
+    );
 }
 
-fn parse_edgedata_vec(input: Vec<f64>) -> Option<EdgeData> {
-    match input.len() {
-        2 => Some(EdgeData::Edge((input[0] as usize, input[1] as usize))),
-        _ => None,
-    }
+fn parse_edgedata_vec<'a>(input: &[&'a str]) -> RowResult<'a, EdgeData> {
+    parse_edge_vec(input).map(EdgeData::Edge)
 }
 
-fn parse_adjacency_vec(input: Vec<f64>) -> Option<EdgeData> {
-    match input.len() {
-        len if len < 2 => None,
-        _ => Some(EdgeData::Adj(
-            input
-                .into_iter()
-                .map(|i| i as usize)
-                .collect::<Vec<usize>>(),
-        )),
+fn parse_adjacency_vec<'a>(input: &[&'a str]) -> RowResult<'a, EdgeData> {
+    match without_terminator(input) {
+        [] => Err(RowError::Length(input.len())),
+        nodes => nodes
+            .iter()
+            .map(|&node| integer(node))
+            .collect::<RowResult<Adj>>()
+            .map(EdgeData::Adj),
     }
 }
 fn combine_demands(demands: Vec<Demand>, dimension: usize) -> Vec<u32> {
     let mut res = vec![0; dimension];
     for demand in demands {
-        res[demand.0 - 1] = demand.1 //TSPLIB counts from 1
+        //TSPLIB counts from 1. The validation pass has already complained about ids out of range.
+        if let Some(slot) = demand.0.checked_sub(1).and_then(|i| res.get_mut(i)) {
+            *slot = demand.1
+        }
     }
     res
 }
@@ -352,107 +434,688 @@ fn combine_adjacencies(
     for edge_row in edges {
         if let EdgeData::Adj(adj_row) = edge_row {
             if let Some((node_id, adjacencies)) = adj_row.split_first() {
-                let mut adj_vec = adjacencies.to_vec();
-                adj_vec.sort();
-                res[*node_id - 1] = EdgeData::Adj(adj_vec); //TSPLIB counts from 1
+                //TSPLIB counts from 1. Rows of nodes out of range have already been complained about.
+                if let Some(slot) = node_id.checked_sub(1).and_then(|i| res.get_mut(i)) {
+                    let mut adj_vec = adjacencies.to_vec();
+                    adj_vec.sort();
+                    *slot = EdgeData::Adj(adj_vec);
+                }
             }
         }
     }
     res
 }
-fn ignore_vec<T>(input: Vec<f64>) -> Option<T> {
-    println!(
-        "Warning: Going to ignore this row because its format is unknown: {:?}",
-        input
-    );
-    None
+//Runs a line parser on a row of numbers and adds what it gives to the section's rows.
+fn push_row<'a, T>(
+    rows: &mut Option<Vec<T>>,
+    numbers: &[&'a str],
+    line_parser: fn(&[&'a str]) -> RowResult<'a, T>,
+) -> RowResult<'a, ()> {
+    let row = line_parser(numbers)?;
+    rows.get_or_insert_with(Vec::new).push(row);
+    Ok(())
 }
 
-fn parse_data_section<'a>(input: &'a str, header: TSPLMeta) -> IResult<&'a str, TSPLProblem> {
-    //Here we should be building a list of sections that we are expecting based
-    //on the header data. At the moment we are making every section optional,
-    //Or silently ignoring data if the format is not set (which is not ideal).
-    let edge_parser = match header.edge_data_format {
-        Some(EdgeDataFormat::ADJ_LIST) => parse_adjacency_vec,
-        Some(EdgeDataFormat::EDGE_LIST) => parse_edgedata_vec,
-        None => ignore_vec, //TODO: omit the EDGE_DATA_SECTION if there is no Format for it
-    };
-    //TODO: Be a bit smarter about parser type here. NO_COORDS can be the type, but the problem can be EUC2D
-    //and it will expect a NODE_COORD_SECTION.
-    let coord_parser = match header.node_coord_type {
-        NodeCoordType::THREED_COORDS => parse_coord3_vec,
-        NodeCoordType::TWOD_COORDS => parse_coord2_vec,
-        NodeCoordType::NO_COORDS => parse_coord2_vec,
-    };
-    map!(
-        input,
-        permutation!(
-            complete!(call!(get_section, "NODE_COORD_SECTION", coord_parser))?,
-            complete!(call!(get_section, "DEPOT_SECTION", parse_depot_vec))?,
-            complete!(call!(get_section, "DEMAND_SECTION", parse_demand_vec))?,
-            complete!(call!(get_section, "EDGE_DATA_SECTION", edge_parser))?,
-            complete!(call!(get_section, "FIXED_EDGES_SECTION", parse_edge_vec))?,
-            complete!(call!(get_section, "DISPLAY_DATA_SECTION", parse_coord2_vec))?, //TODO only call this parser if DISPLAY_DATA_TYPE is TWOD_COORDS
-            complete!(call!(get_section, "TOUR_SECTION", parse_tour_vec))?,
-            complete!(call!(get_section, "EDGE_WEIGHT_SECTION", parse_weights_vec))?
-        ),
-        |(
-            node_coordinates,
-            depots,
-            demands,
-            edges,
-            fixed_edges,
-            display_data,
-            tours,
-            edge_weights,
-        ): (
-            Option<Vec<Coord>>,
-            Option<Vec<usize>>,
-            Option<Vec<Demand>>,
-            Option<Vec<EdgeData>>,
-            Option<Vec<Edge>>,
-            Option<Vec<Coord>>,
-            Option<Vec<Tour>>,
-            Option<Vec<EdgeWeightList>>,
-        )| {
-            TSPLProblem {
-                header: header.clone(),
-                data: TSPLData {
-                    node_coordinates,
-                    depots,
-                    demands: demands.map(|d| combine_demands(d, header.dimension as usize)),
-                    display_data,
-                    edge_weights: edge_weights.map(|ew| ew.concat()), //.and_then(build_distance_matrix),
-                    edges: edges.map(|es| {
-                        combine_adjacencies(
-                            es,
-                            &header.edge_data_format.clone().unwrap(),
-                            &(header.dimension as usize),
-                        )
-                    }),
-                    fixed_edges,
-                    tours,
-                },
+//Goes through a file one line at a time: first the header lines, and once the first
+//section title shows up, the data sections. Keeping track of where we are lets every
+//error say which line, keyword and section it comes from.
+#[derive(Default)]
+struct Parser {
+    fields: HeaderFields,
+    //Set once the header is over.
+    header: Option<TSPLMeta>,
+    section: Option<Section>,
+    //The line and column each section title was on, so validation errors can point at them.
+    section_starts: Vec<(Section, usize, usize)>,
+    //Whether we are in a section this parser doesn't know, whose rows get skipped.
+    unknown_section: bool,
+    //The number of the last line we've seen.
+    line: usize,
+    eof: bool,
+    strict: bool,
+    //Turns off the fast path for the big sections, for tests that compare it with the generic one.
+    generic_rows: bool,
+    diagnostics: Vec<Diagnostic>,
+    node_coordinates: Option<Vec<Coord>>,
+    depots: Option<Vec<usize>>,
+    demands: Option<Vec<Demand>>,
+    edges: Option<Vec<EdgeData>>,
+    fixed_edges: Option<Vec<Edge>>,
+    display_data: Option<Vec<Coord>>,
+    tours: Option<Vec<Tour>>,
+    //The tour being read, until its -1 shows up.
+    tour: Tour,
+    //For a TOUR file without DIMENSION, which is then the length of its tour.
+    dimension_from_tour: bool,
+    //NODE_COORD_TYPE and DISPLAY_DATA_TYPE, if the header has them, to check against the data.
+    stated_coord_type: Option<NodeCoordType>,
+    stated_display_type: Option<DisplayDataType>,
+    //Whether the rows of NODE_COORD_SECTION are 2d or 3d. The header says, or else the first row.
+    coord_type: Option<NodeCoordType>,
+    edge_weights: Option<EdgeWeightList>,
+}
+
+impl Parser {
+    //Reads the next line of the file, without its line ending.
+    fn line(&mut self, line: &str) -> Result<(), TsplibError> {
+        self.line += 1;
+        //Some Windows editors start the file with a byte order mark.
+        let line = match self.line {
+            1 => line.trim_start_matches('\u{feff}'),
+            _ => line,
+        };
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            return Ok(());
+        }
+        let column = 1 + line.len() - line.trim_start().len();
+        //EOF, eof, EOF: and the like, which some files have more than one of.
+        let is_eof = title(trimmed).eq_ignore_ascii_case("EOF");
+        if self.eof && !is_eof {
+            //Only say it once.
+            let reported = self
+                .diagnostics
+                .last()
+                .is_some_and(|d| d.kind == DiagnosticKind::TextAfterEof);
+            return match reported {
+                true => Ok(()),
+                false => self.warn(column, None, DiagnosticKind::TextAfterEof),
+            };
+        }
+        if is_eof {
+            self.eof = true;
+            return Ok(());
+        }
+        let upper = title(trimmed).to_ascii_uppercase();
+        if let Ok(section) = upper.parse::<Section>() {
+            return self.start_section(section, column);
+        }
+        if is_section_title(&upper) {
+            self.finish_header()?;
+            self.section = None;
+            self.unknown_section = true;
+            return self.warn(
+                column,
+                None,
+                DiagnosticKind::UnknownSection(trimmed.to_string()),
+            );
+        }
+        if trimmed.contains(':') {
+            if self.header.is_none() {
+                return self.fields.parse_line(trimmed, self.line, column);
+            }
+            let key = trimmed.split(':').next().unwrap_or("").trim_end();
+            let e = TsplibError::new(
+                self.line,
+                column,
+                TsplibErrorKind::HeaderAfterData(key.to_string()),
+            );
+            return Err(match self.section {
+                Some(section) => e.context(&section.to_string()),
+                None => e,
+            });
+        }
+        match self.section {
+            _ if self.unknown_section => Ok(()),
+            None => Err(TsplibError::new(
+                self.line,
+                column,
+                TsplibErrorKind::DataOutsideSection,
+            )),
+            Some(section) => self
+                .data_row(section, trimmed, column)
+                .map_err(|e| e.context(&section.to_string())),
+        }
+    }
+
+    //Keeps a diagnostic on the current line, or fails with it when parsing strictly.
+    fn warn(
+        &mut self,
+        column: usize,
+        context: Option<Section>,
+        kind: DiagnosticKind,
+    ) -> Result<(), TsplibError> {
+        let diagnostic = Diagnostic {
+            line: self.line,
+            column,
+            context: context.map(|s| s.to_string()),
+            kind,
+        };
+        if self.strict {
+            return Err(diagnostic.into());
+        }
+        self.diagnostics.push(diagnostic);
+        Ok(())
+    }
+
+    //Builds the TSPLMeta once the header is over, noting the fields we had to make up.
+    fn finish_header(&mut self) -> Result<(), TsplibError> {
+        if self.header.is_some() {
+            return Ok(());
+        }
+        //A TOUR file has no weights, so it doesn't need an EDGE_WEIGHT_TYPE.
+        let is_tour = self.fields.problem_type == Some(ProblemType::TOUR);
+        let defaults = [
+            ("NAME", self.fields.name.is_none(), ""),
+            (
+                "EDGE_WEIGHT_TYPE",
+                self.fields.edge_weight_type.is_none() && !is_tour,
+                "EUC_2D",
+            ),
+        ];
+        for (key, missing, value) in defaults.iter() {
+            if *missing {
+                let kind = DiagnosticKind::DefaultedField {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                };
+                self.warn(1, None, kind)?;
             }
         }
-    )
+        let fields = std::mem::take(&mut self.fields);
+        self.dimension_from_tour = fields.dimension.is_none();
+        self.stated_coord_type = fields.node_coord_type.clone();
+        self.stated_display_type = fields.display_data_type.clone();
+        self.header = Some(fields.finish(self.line.max(1))?);
+        Ok(())
+    }
+
+    //Tours go on for as many lines as they need, and each of them ends with a -1.
+    fn tour_row<'a>(&mut self, numbers: &[&'a str]) -> RowResult<'a, ()> {
+        for &token in numbers {
+            match token {
+                "-1" => self.end_tour(),
+                node => self.tour.push(integer(node)?),
+            }
+        }
+        Ok(())
+    }
+
+    fn end_tour(&mut self) {
+        //A -1 without a tour before it ends the section.
+        if !self.tour.is_empty() {
+            let tour = std::mem::take(&mut self.tour);
+            self.tours.get_or_insert_with(Vec::new).push(tour);
+        }
+    }
+
+    //Keeps a tour that the section or the file ended before its -1.
+    fn close_tour(&mut self) -> Result<(), TsplibError> {
+        if !self.tour.is_empty() {
+            self.warn(
+                1,
+                Some(Section::TOUR_SECTION),
+                DiagnosticKind::UnterminatedTour,
+            )?;
+            self.end_tour();
+        }
+        Ok(())
+    }
+
+    fn start_section(&mut self, section: Section, column: usize) -> Result<(), TsplibError> {
+        self.finish_header()?;
+        self.close_tour()?;
+        let started = match section {
+            Section::NODE_COORD_SECTION => self.node_coordinates.is_some(),
+            Section::DEPOT_SECTION => self.depots.is_some(),
+            Section::DEMAND_SECTION => self.demands.is_some(),
+            Section::EDGE_DATA_SECTION => self.edges.is_some(),
+            Section::FIXED_EDGES_SECTION => self.fixed_edges.is_some(),
+            Section::DISPLAY_DATA_SECTION => self.display_data.is_some(),
+            Section::TOUR_SECTION => self.tours.is_some(),
+            Section::EDGE_WEIGHT_SECTION => self.edge_weights.is_some(),
+        };
+        if started {
+            return Err(TsplibError::new(
+                self.line,
+                column,
+                TsplibErrorKind::Duplicate(section.to_string()),
+            ));
+        }
+        self.section_starts.push((section, self.line, column));
+        //The header says how big the biggest sections will be, up to a point in case it's wrong.
+        let header = self.header.as_ref().unwrap();
+        let capacity = |count: usize| count.min(MAX_PREALLOCATED);
+        let dimension = header.dimension as usize;
+        //An empty section is still there, so make sure it is Some(vec![]) instead of None.
+        match section {
+            Section::NODE_COORD_SECTION => {
+                self.node_coordinates = Some(Vec::with_capacity(capacity(dimension)));
+                self.coord_type = match &self.stated_coord_type {
+                    Some(NodeCoordType::NO_COORDS) | None => {
+                        header.edge_weight_type.node_coord_type()
+                    }
+                    stated => stated.clone(),
+                };
+            }
+            Section::DEPOT_SECTION => self.depots = Some(vec![]),
+            Section::DEMAND_SECTION => self.demands = Some(vec![]),
+            Section::EDGE_DATA_SECTION => self.edges = Some(vec![]),
+            Section::FIXED_EDGES_SECTION => self.fixed_edges = Some(vec![]),
+            Section::DISPLAY_DATA_SECTION => self.display_data = Some(vec![]),
+            Section::TOUR_SECTION => self.tours = Some(vec![]),
+            Section::EDGE_WEIGHT_SECTION => {
+                let count = header
+                    .edge_weight_format
+                    .as_ref()
+                    .and_then(|ewf| entry_count(ewf, dimension))
+                    .unwrap_or(0);
+                self.edge_weights = Some(Vec::with_capacity(capacity(count)))
+            }
+        }
+        self.section = Some(section);
+        self.unknown_section = false;
+        Ok(())
+    }
+
+    //NODE_COORD_SECTION and EDGE_WEIGHT_SECTION are nearly all of a big file. Their rows are
+    //read straight into the section, without collecting the numbers of each row first.
+    //Gives false when the row needs a closer look, which the generic path gives it.
+    fn fast_row(&mut self, section: Section, row: &str) -> bool {
+        match section {
+            Section::NODE_COORD_SECTION => {
+                let three_d = self.coord_type == Some(NodeCoordType::THREED_COORDS);
+                match (fast_coord(row, three_d), self.node_coordinates.as_mut()) {
+                    (Some(coord), Some(coords)) => {
+                        coords.push(coord);
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            Section::EDGE_WEIGHT_SECTION => {
+                let weights = self.edge_weights.get_or_insert_with(Vec::new);
+                let len = weights.len();
+                for token in tokens(row) {
+                    match token.parse() {
+                        Ok(weight) => weights.push(weight),
+                        Err(_) => {
+                            weights.truncate(len);
+                            return false;
+                        }
+                    }
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn data_row(&mut self, section: Section, row: &str, column: usize) -> Result<(), TsplibError> {
+        //Without NODE_COORD_TYPE or an EDGE_WEIGHT_TYPE that needs coordinates, the first row
+        //says which ones the section has: an id and 2 or 3 numbers.
+        if section == Section::NODE_COORD_SECTION && self.coord_type.is_none() {
+            self.coord_type = match tokens(row).count() {
+                4 => Some(NodeCoordType::THREED_COORDS),
+                _ => Some(NodeCoordType::TWOD_COORDS),
+            };
+        }
+        if !self.generic_rows && self.fast_row(section, row) {
+            return Ok(());
+        }
+        let numbers = match numbers_on_line(row) {
+            Ok(("", numbers)) => numbers,
+            Ok((rest, _)) => {
+                let rest = rest.trim_start();
+                let found = rest.split_whitespace().next().unwrap_or(rest);
+                return Err(TsplibError::new(
+                    self.line,
+                    column + row.len() - rest.len(),
+                    TsplibErrorKind::InvalidNumber(found.to_string()),
+                ));
+            }
+            Err(_) => {
+                let found = row.split_whitespace().next().unwrap_or(row);
+                return Err(TsplibError::new(
+                    self.line,
+                    column,
+                    TsplibErrorKind::InvalidNumber(found.to_string()),
+                ));
+            }
+        };
+        if numbers == ["-1"] && section != Section::TOUR_SECTION {
+            return Ok(());
+        }
+        //The header is always there once a section has started.
+        let header = self.header.as_ref().unwrap();
+        let res = match section {
+            Section::NODE_COORD_SECTION => match self.coord_type {
+                Some(NodeCoordType::THREED_COORDS) => {
+                    push_row(&mut self.node_coordinates, &numbers, parse_coord3_vec)
+                }
+                _ => push_row(&mut self.node_coordinates, &numbers, parse_coord2_vec),
+            },
+            Section::DEPOT_SECTION => push_row(&mut self.depots, &numbers, parse_depot_vec),
+            Section::DEMAND_SECTION => push_row(&mut self.demands, &numbers, parse_demand_vec),
+            Section::EDGE_DATA_SECTION => match header.edge_data_format {
+                Some(EdgeDataFormat::ADJ_LIST) => {
+                    push_row(&mut self.edges, &numbers, parse_adjacency_vec)
+                }
+                Some(EdgeDataFormat::EDGE_LIST) => {
+                    push_row(&mut self.edges, &numbers, parse_edgedata_vec)
+                }
+                //TODO: omit the EDGE_DATA_SECTION if there is no Format for it
+                None => return self.warn(column, Some(section), DiagnosticKind::SkippedRow),
+            },
+            Section::FIXED_EDGES_SECTION => {
+                push_row(&mut self.fixed_edges, &numbers, parse_edge_vec)
+            }
+            //The spec only has 2d display data.
+            Section::DISPLAY_DATA_SECTION => {
+                push_row(&mut self.display_data, &numbers, parse_coord2_vec)
+            }
+            Section::TOUR_SECTION => self.tour_row(&numbers),
+            //A -1 can end the last row of weights, instead of coming on its own line.
+            Section::EDGE_WEIGHT_SECTION => {
+                parse_weights_vec(without_terminator(&numbers)).map(|weights| {
+                    self.edge_weights
+                        .get_or_insert_with(Vec::new)
+                        .extend(weights)
+                })
+            }
+        };
+        //The numbers are slices of the row, which gives the column of a bad one.
+        let at = |token: &str| column + (token.as_ptr() as usize - row.as_ptr() as usize);
+        res.map_err(|e| match e {
+            RowError::Length(len) => {
+                TsplibError::new(self.line, column, TsplibErrorKind::WrongRowLength(len))
+            }
+            RowError::NotAnInteger(token) => TsplibError::new(
+                self.line,
+                at(token),
+                TsplibErrorKind::NotAnInteger(token.to_string()),
+            ),
+            RowError::NotANumber(token) => TsplibError::new(
+                self.line,
+                at(token),
+                TsplibErrorKind::InvalidNumber(token.to_string()),
+            ),
+        })
+    }
+
+    //Works out NODE_COORD_TYPE and DISPLAY_DATA_TYPE from the sections the file has. The
+    //header can leave them out, but when it says something the data doesn't go with, the
+    //data wins and we say so.
+    fn settle_coord_types(&mut self, header: &mut TSPLMeta) -> Result<(), TsplibError> {
+        let coords = self.node_coordinates.is_some();
+        let display_data = self.display_data.is_some();
+        let coord_type = match self.coord_type.take() {
+            Some(coord_type) if coords => coord_type,
+            //An empty NODE_COORD_SECTION.
+            None if coords => NodeCoordType::TWOD_COORDS,
+            _ => NodeCoordType::NO_COORDS,
+        };
+        let display_type = match (display_data, coords) {
+            (true, _) => DisplayDataType::TWOD_DISPLAY,
+            (false, true) => DisplayDataType::COORDS_DISPLAY,
+            (false, false) => DisplayDataType::NO_DISPLAY,
+        };
+        let display_fits = |stated: &DisplayDataType| match stated {
+            DisplayDataType::COORDS_DISPLAY => coords && !display_data,
+            DisplayDataType::TWOD_DISPLAY => display_data,
+            DisplayDataType::NO_DISPLAY => !display_data,
+        };
+        if let Some(stated) = self.stated_coord_type.take() {
+            if stated != coord_type {
+                let kind = DiagnosticKind::NodeCoordTypeMismatch {
+                    header: stated,
+                    data: coord_type.clone(),
+                };
+                self.warn(1, None, kind)?;
+            }
+        }
+        header.node_coord_type = coord_type;
+        header.display_data_type = match self.stated_display_type.take() {
+            Some(stated) if display_fits(&stated) => stated,
+            Some(stated) => {
+                let kind = DiagnosticKind::DisplayDataTypeMismatch {
+                    header: stated,
+                    data: display_type.clone(),
+                };
+                self.warn(1, None, kind)?;
+                display_type
+            }
+            None => display_type,
+        };
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(TSPLProblem, Vec<Diagnostic>), TsplibError> {
+        self.finish_header()?;
+        self.close_tour()?;
+        //finish_header always sets it.
+        let mut header = self.header.take().unwrap();
+        if self.dimension_from_tour {
+            let tour = self.tours.iter().flatten().next();
+            header.dimension = tour.map_or(0, |tour| tour.len() as u32);
+            let kind = DiagnosticKind::DefaultedField {
+                key: "DIMENSION".to_string(),
+                value: header.dimension.to_string(),
+            };
+            self.warn(1, None, kind)?;
+        }
+        self.settle_coord_types(&mut header)?;
+        let dimension = header.dimension as usize;
+        //The demands and adjacency lists lose their ids once they are combined, so they are
+        //checked here.
+        let mut errors = vec![];
+        if let (Some(edges), Some(EdgeDataFormat::ADJ_LIST)) =
+            (&self.edges, &header.edge_data_format)
+        {
+            let ids = edges.iter().filter_map(|row| match row {
+                EdgeData::Adj(adj) => adj.first().map(|&node| node as i64),
+                EdgeData::Edge(_) => None,
+            });
+            validate::check_ids("EDGE_DATA_SECTION", ids, dimension, true, &mut errors);
+        }
+        let edges = match (self.edges, &header.edge_data_format) {
+            (Some(es), Some(edf)) => Some(combine_adjacencies(es, edf, &dimension)),
+            (es, _) => es,
+        };
+        if let Some(demands) = &self.demands {
+            let section = "DEMAND_SECTION";
+            let ids = demands.iter().map(|d| d.0 as i64);
+            validate::check_ids(section, ids, dimension, true, &mut errors);
+            validate::check_count(section, demands.len(), dimension, &mut errors);
+        }
+        let problem = TSPLProblem {
+            data: TSPLData {
+                node_coordinates: self.node_coordinates,
+                depots: self.depots,
+                demands: self.demands.map(|d| combine_demands(d, dimension)),
+                display_data: self.display_data,
+                edge_weights: self.edge_weights,
+                edges,
+                fixed_edges: self.fixed_edges,
+                tours: self.tours,
+            },
+            header,
+        };
+        errors.extend(problem.validate().err().into_iter().flatten());
+        if let Some(first) = errors.first() {
+            //Points at the title of the section the first error is in, or else names the keyword.
+            let context = first.context().to_string();
+            let start = self
+                .section_starts
+                .iter()
+                .find(|s| s.0.to_string() == context);
+            let error = match start {
+                Some(&(_, line, column)) => {
+                    TsplibError::new(line, column, TsplibErrorKind::Invalid(errors))
+                }
+                None => TsplibError::new(0, 0, TsplibErrorKind::Invalid(errors)).context(&context),
+            };
+            return Err(error);
+        }
+        Ok((problem, self.diagnostics))
+    }
+}
+
+//Sections don't get more room than this up front, so that a wrong DIMENSION can't
+//make us allocate gigabytes.
+const MAX_PREALLOCATED: usize = 1 << 24;
+
+//The numbers of a row, split where numbers_on_line splits them.
+fn tokens(row: &str) -> impl Iterator<Item = &str> {
+    row.split([' ', '\t']).filter(|t| !t.is_empty())
+}
+
+//Reads a row of NODE_COORD_SECTION, or gives None if something is off with it.
+//f64::from_str takes the same numbers as numbers_on_line, plus inf and NaN, which real() refuses.
+fn fast_coord(row: &str, three_d: bool) -> Option<Coord> {
+    let mut tokens = tokens(row);
+    let id = tokens.next()?.parse().ok()?;
+    let mut real = || {
+        let x: f64 = tokens.next()?.parse().ok()?;
+        Some(x).filter(|x| x.is_finite()).map(n64)
+    };
+    let coord = match three_d {
+        true => Coord::Coord3(id, real()?, real()?, real()?),
+        false => Coord::Coord2(id, real()?, real()?),
+    };
+    match tokens.next() {
+        None => Some(coord),
+        Some(_) => None,
+    }
+}
+
+//A section title or EOF can have a colon after it, like `TOUR_SECTION :`.
+fn title(line: &str) -> &str {
+    line.strip_suffix(':').map_or(line, str::trim_end)
 }
+
+//A line like NODE_COORD_SECTION, for the sections we don't know.
+fn is_section_title(line: &str) -> bool {
+    line.ends_with("_SECTION") && is_keyword(line)
+}
+
+///How the parser deals with what it can work around.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct ParseOptions {
+    ///Fail on the first `Diagnostic`, instead of collecting them.
+    pub strict: bool,
+}
+
+///A parsed problem, with everything odd the parser found along the way.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Parsed {
+    pub problem: TSPLProblem,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+///Parse a `&str` into a `TSPLProblem` and its `Diagnostic`s, or a `TsplibError` saying where
+///it went wrong.
+pub fn parse_str_with(input: &str, options: &ParseOptions) -> Result<Parsed, TsplibError> {
+    let mut parser = Parser {
+        strict: options.strict,
+        ..Parser::default()
+    };
+    for line in input.lines() {
+        parser.line(line)?;
+    }
+    let (problem, diagnostics) = parser.finish()?;
+    Ok(Parsed {
+        problem,
+        diagnostics,
+    })
+}
+
+///Parse a problem from anything that can be read a line at a time, like a `File`, stdin or a
+///socket in a `BufReader`, without holding all of it in memory. See `parse_str_with`.
+///
+///With the `gzip` feature, gzip compressed input is decompressed on the fly. It is told apart
+///by its first bytes, whatever the file is called.
+pub fn parse_reader_with<R: BufRead>(
+    reader: R,
+    options: &ParseOptions,
+) -> Result<Parsed, TsplibError> {
+    let io_error = |line: usize, e: io::Error| {
+        TsplibError::new(line, line.min(1), TsplibErrorKind::Io(e.to_string()))
+    };
+    let mut reader = reader::decompress(reader).map_err(|e| io_error(0, e))?;
+    let mut parser = Parser {
+        strict: options.strict,
+        ..Parser::default()
+    };
+    let mut line = String::new();
+    while reader::next_line(&mut reader, &mut line).map_err(|e| io_error(parser.line + 1, e))? {
+        parser.line(&line)?;
+    }
+    let (problem, diagnostics) = parser.finish()?;
+    Ok(Parsed {
+        problem,
+        diagnostics,
+    })
+}
+
+///Read and parse a file a line at a time, see `parse_reader_with`. Errors carry the path
+///of the file.
+pub fn parse_path_with<P: AsRef<Path>>(
+    path: P,
+    options: &ParseOptions,
+) -> Result<Parsed, TsplibError> {
+    let path = path.as_ref();
+    let with_path = |mut e: TsplibError| {
+        e.path = Some(path.display().to_string());
+        e
+    };
+    let file = File::open(path)
+        .map_err(|e| with_path(TsplibError::new(0, 0, TsplibErrorKind::Io(e.to_string()))))?;
+    parse_reader_with(BufReader::new(file), options).map_err(with_path)
+}
+
+///Parse a `&str` into a `TSPLProblem`, or a `TsplibError` saying where it went wrong.
+///Diagnostics are dropped, use `parse_str_with` to get them.
+pub fn parse_str(input: &str) -> Result<TSPLProblem, TsplibError> {
+    parse_str_with(input, &ParseOptions::default()).map(|parsed| parsed.problem)
+}
+
+///Parse a problem a line at a time, see `parse_reader_with`.
+pub fn parse_reader<R: BufRead>(reader: R) -> Result<TSPLProblem, TsplibError> {
+    parse_reader_with(reader, &ParseOptions::default()).map(|parsed| parsed.problem)
+}
+
+///Read and parse a file, see `parse_str`. Errors carry the path of the file.
+pub fn parse_path<P: AsRef<Path>>(path: P) -> Result<TSPLProblem, TsplibError> {
+    parse_path_with(path, &ParseOptions::default()).map(|parsed| parsed.problem)
+}
+
+///Parse a `&str` into a `TSPLProblem`, the nom way.
+///This is kept for compatibility, `parse_str` gives errors that say what went wrong.
+pub fn parse_problem(input: &str) -> IResult<&str, TSPLProblem> {
+    parse_str(input)
+        .map(|problem| ("", problem))
+        .map_err(|_| Err::Error((input, nom::error::ErrorKind::ParseTo)))
+}
+
+impl FromStr for TSPLProblem {
+    type Err = TsplibError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_str(s)
+    }
+}
+
+///Parse the contents of a file into an `Option<TSPLProblem>`.
+///This is kept for compatibility, `parse_path` says why a file didn't parse.
+pub fn parse_file(filename: &str) -> Option<TSPLProblem> {
+    parse_path(filename).ok()
+}
+
 #[cfg(test)]
 mod tests {
     // Note this useful idiom: importing names from outer (for mod tests) scope.
     use super::*;
     use std::fmt::{Debug, Display};
 
-    fn test_kv<'a, G: std::str::FromStr + Display + Debug + PartialEq + Clone + 'a>(
-        key: &str,
-        value: G,
-    ) {
-        let input = format!("{}: {}\n", key, value);
-        let output: IResult<&str, G> = kv_parse(&input, key);
-        assert_eq!(output, Ok(("", value)));
+    fn test_kv<G: std::str::FromStr + Display + Debug + PartialEq>(key: &str, value: G) {
+        let input = format!("{}: {}", key, value);
+        let (k, v) = key_value(&input).unwrap();
+        assert_eq!(k, key);
+        assert_eq!(v.parse::<G>().ok(), Some(value));
     }
 
-    //Will give kv_parse a KEY: VALUE input and see if it roundtrips ok.
+    //Will split a KEY: VALUE input and see if the value roundtrips ok.
     #[test]
     fn test_some_kvs() {
         test_kv("NAME", "some_name".to_string());
@@ -485,8 +1148,8 @@ mod tests {
     fn test_parse_data_section() {
         let header = TSPLMeta {
             comment: "".to_string(),
-            dimension: 0,
-            display_data_type: DisplayDataType::NO_DISPLAY,
+            dimension: 3,
+            display_data_type: DisplayDataType::TWOD_DISPLAY,
             name: "TEST".to_string(),
             problem_type: ProblemType::TSP,
             capacity: None,
@@ -494,6 +1157,7 @@ mod tests {
             edge_weight_format: None,
             edge_data_format: None,
             node_coord_type: NodeCoordType::TWOD_COORDS,
+            extra: vec![],
         };
         let ncs = "NODE_COORD_SECTION
 1 565.0 575.0
@@ -517,44 +1181,390 @@ EOF
             Coord::Coord2(2, n64(125.0), n64(80.0)),
             Coord::Coord2(3, n64(97.0), n64(74.0)),
         ]);
+        let mut parser = Parser {
+            header: Some(header.clone()),
+            ..Parser::default()
+        };
+        for line in ncs.lines() {
+            parser.line(line).unwrap();
+        }
         assert_eq!(
-            parse_data_section(ncs, header.clone()),
+            parser.finish(),
             Ok((
-                "",
                 TSPLProblem {
                     header: header.clone(),
                     data: t,
-                }
+                },
+                vec![]
             ))
         );
     }
 
-    fn parse_problem_opt(input: String) -> Option<TSPLProblem> {
-        let r_tuple = parse_problem(&input);
-        r_tuple.map(|x| x.1).ok()
+    fn error_at(input: &str) -> (usize, usize, Option<String>, TsplibErrorKind) {
+        let e = parse_str(input).unwrap_err();
+        (e.line, e.column, e.context, e.kind)
     }
-}
 
-///Parse a `&str` into a `Result<TSPLroblem>`
-pub fn parse_problem<'a>(input: &'a str) -> IResult<&'a str, TSPLProblem> {
-    parse_header(input).and_then(|(input, header)| parse_data_section(input, header))
-}
+    #[test]
+    fn test_error_locations() {
+        assert_eq!(
+            error_at("NAME: x\nTYPE: TSP\nDIMENSION: 5O\n"),
+            (
+                3,
+                12,
+                Some("DIMENSION".to_string()),
+                TsplibErrorKind::InvalidValue("5O".to_string())
+            )
+        );
+        assert_eq!(
+            error_at("TYPE: TSP\nMY KEY : 3\n"),
+            (
+                2,
+                1,
+                None,
+                TsplibErrorKind::UnknownKeyword("MY KEY".to_string())
+            )
+        );
+        assert_eq!(
+            error_at("TYPE: TSP\nDIMENSION: 2\nNODE_COORD_SECTION\n1 0 0\n  2 0,5 1\n"),
+            (
+                5,
+                6,
+                Some("NODE_COORD_SECTION".to_string()),
+                TsplibErrorKind::InvalidNumber(",5".to_string())
+            )
+        );
+        assert_eq!(
+            error_at("TYPE: TSP\nDIMENSION: 2\nNODE_COORD_SECTION\n1 0\n"),
+            (
+                4,
+                1,
+                Some("NODE_COORD_SECTION".to_string()),
+                TsplibErrorKind::WrongRowLength(2)
+            )
+        );
+        assert_eq!(
+            error_at("TYPE: CVRP\nDIMENSION: 1\nDEMAND_SECTION\n1 -3\n"),
+            (
+                4,
+                3,
+                Some("DEMAND_SECTION".to_string()),
+                TsplibErrorKind::NotAnInteger("-3".to_string())
+            )
+        );
+        assert_eq!(
+            error_at("TYPE: TSP\nDIMENSION: 2\nNODE_COORD_SECTION\n1 0 0\n2.0 1 1\n").3,
+            TsplibErrorKind::NotAnInteger("2.0".to_string())
+        );
+        assert_eq!(
+            error_at("TYPE: TSP\nNODE_COORD_SECTION\n"),
+            (
+                2,
+                1,
+                None,
+                TsplibErrorKind::MissingKeyword("DIMENSION".to_string())
+            )
+        );
+        assert_eq!(
+            error_at("TYPE: TSP\nTYPE: TSP\n").3,
+            TsplibErrorKind::Duplicate("TYPE".to_string())
+        );
+    }
 
-//This is incorrect, because I don't know how to pull out the ErrorKind from an IResult
-impl FromStr for TSPLProblem {
-    type Err = nom::error::ErrorKind;
+    #[test]
+    fn test_error_display() {
+        let e = parse_str("TYPE: TSP\nDIMENSION: x\n").unwrap_err();
+        assert_eq!(e.to_string(), "2:12: in DIMENSION: invalid value \"x\"");
+        let e = TsplibError {
+            path: Some("a.tsp".to_string()),
+            ..e
+        };
+        assert_eq!(
+            e.to_string(),
+            "a.tsp:2:12: in DIMENSION: invalid value \"x\""
+        );
+        assert_eq!(e.message(), "invalid value \"x\"");
+    }
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match parse_problem(s) {
-            Ok(x) => Ok(x.1),
-            Err(_) => Err(nom::error::ErrorKind::ParseTo),
+    #[test]
+    fn test_extra_keywords() {
+        let header = parse_str(
+            "NAME : E-n3-k2
+VEHICLES : 2
+TYPE : CVRP
+DIMENSION : 3
+SERVICE_TIME : 1.5 per stop
+CAPACITY : 10
+",
+        )
+        .unwrap()
+        .header;
+        assert_eq!(header.problem_type, ProblemType::CVRP);
+        assert_eq!(header.capacity, Some(10));
+        assert_eq!(
+            header.extra,
+            vec![
+                ("VEHICLES".to_string(), "2".to_string()),
+                ("SERVICE_TIME".to_string(), "1.5 per stop".to_string()),
+            ]
+        );
+        assert_eq!(header.extra_value("SERVICE_TIME"), Some("1.5 per stop"));
+        assert_eq!(header.extra_value("DISTANCE"), None);
+        assert_eq!(
+            error_at("TYPE: TSP\nDIMENSION: 1\nSALESMEN: 2\nSALESMEN: 3\n"),
+            (
+                4,
+                1,
+                Some("SALESMEN".to_string()),
+                TsplibErrorKind::Duplicate("SALESMEN".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let input = "TYPE: TSP
+DIMENSION: 3
+EDGE_DATA_SECTION
+1 2
+VEHICLE_SECTION
+1 2 3
+TOUR_SECTION
+1 2 3 -1
+EOF
+junk
+";
+        let parsed = parse_str_with(input, &ParseOptions::default()).unwrap();
+        let found: Vec<_> = parsed
+            .diagnostics
+            .iter()
+            .map(|d| (d.line, d.column, d.context.as_deref(), d.kind.clone()))
+            .collect();
+        let defaulted = |key: &str, value: &str| DiagnosticKind::DefaultedField {
+            key: key.to_string(),
+            value: value.to_string(),
+        };
+        assert_eq!(
+            found,
+            vec![
+                (3, 1, None, defaulted("NAME", "")),
+                (3, 1, None, defaulted("EDGE_WEIGHT_TYPE", "EUC_2D")),
+                (4, 1, Some("EDGE_DATA_SECTION"), DiagnosticKind::SkippedRow),
+                (
+                    5,
+                    1,
+                    None,
+                    DiagnosticKind::UnknownSection("VEHICLE_SECTION".to_string())
+                ),
+                (10, 1, None, DiagnosticKind::TextAfterEof),
+            ]
+        );
+        assert_eq!(
+            parsed.diagnostics[2].to_string(),
+            "4:1: in EDGE_DATA_SECTION: row skipped because there is no EDGE_DATA_FORMAT"
+        );
+        assert_eq!(parsed.problem.data.tours, Some(vec![vec![1, 2, 3]]));
+    }
+
+    #[test]
+    fn test_coord_types() {
+        let parse = |header: &str, sections: &str| {
+            let input = format!("NAME: x\nTYPE: TSP\nDIMENSION: 2\n{}{}", header, sections);
+            let parsed = parse_str_with(&input, &ParseOptions::default()).unwrap();
+            let header = parsed.problem.header;
+            let kinds: Vec<_> = parsed.diagnostics.into_iter().map(|d| d.kind).collect();
+            (header.node_coord_type, header.display_data_type, kinds)
+        };
+        let coords_2d = "NODE_COORD_SECTION\n1 0 0\n2 1 1\n";
+        let coords_3d = "NODE_COORD_SECTION\n1 0 0 0\n2 1 1 1\n";
+        let display = "DISPLAY_DATA_SECTION\n1 0 0\n2 1 1\n";
+        let weights = "EDGE_WEIGHT_SECTION\n1\n";
+        let explicit = "EDGE_WEIGHT_TYPE: EXPLICIT\nEDGE_WEIGHT_FORMAT: UPPER_ROW\n";
+
+        use DisplayDataType::*;
+        use NodeCoordType::*;
+        //Without NODE_COORD_TYPE, the EDGE_WEIGHT_TYPE or the rows say.
+        assert_eq!(
+            parse("EDGE_WEIGHT_TYPE: EUC_2D\n", coords_2d),
+            (TWOD_COORDS, COORDS_DISPLAY, vec![])
+        );
+        assert_eq!(
+            parse("EDGE_WEIGHT_TYPE: EUC_3D\n", coords_3d),
+            (THREED_COORDS, COORDS_DISPLAY, vec![])
+        );
+        let both = format!("{}{}", weights, coords_3d);
+        assert_eq!(
+            parse(explicit, &both),
+            (THREED_COORDS, COORDS_DISPLAY, vec![])
+        );
+        let both = format!("{}{}", weights, display);
+        assert_eq!(parse(explicit, &both), (NO_COORDS, TWOD_DISPLAY, vec![]));
+        assert_eq!(parse(explicit, weights), (NO_COORDS, NO_DISPLAY, vec![]));
+
+        //Both spellings of COORD_DISPLAY.
+        for spelling in ["COORD_DISPLAY", "COORDS_DISPLAY"].iter() {
+            let header = format!("EDGE_WEIGHT_TYPE: GEO\nDISPLAY_DATA_TYPE: {}\n", spelling);
+            assert_eq!(
+                parse(&header, coords_2d),
+                (TWOD_COORDS, COORDS_DISPLAY, vec![])
+            );
+        }
+        assert_eq!(COORDS_DISPLAY.to_string(), "COORD_DISPLAY");
+
+        //When the header and the data disagree, the data wins.
+        let header = "EDGE_WEIGHT_TYPE: EUC_2D\nNODE_COORD_TYPE: NO_COORDS\n";
+        assert_eq!(
+            parse(header, coords_2d),
+            (
+                TWOD_COORDS,
+                COORDS_DISPLAY,
+                vec![DiagnosticKind::NodeCoordTypeMismatch {
+                    header: NO_COORDS,
+                    data: TWOD_COORDS
+                }]
+            )
+        );
+        let header = format!("{}DISPLAY_DATA_TYPE: TWOD_DISPLAY\n", explicit);
+        assert_eq!(
+            parse(&header, weights),
+            (
+                NO_COORDS,
+                NO_DISPLAY,
+                vec![DiagnosticKind::DisplayDataTypeMismatch {
+                    header: TWOD_DISPLAY,
+                    data: NO_DISPLAY
+                }]
+            )
+        );
+        let header = "EDGE_WEIGHT_TYPE: EUC_2D\nDISPLAY_DATA_TYPE: COORD_DISPLAY\n";
+        let both = format!("{}{}", coords_2d, display);
+        assert_eq!(
+            parse(header, &both),
+            (
+                TWOD_COORDS,
+                TWOD_DISPLAY,
+                vec![DiagnosticKind::DisplayDataTypeMismatch {
+                    header: COORDS_DISPLAY,
+                    data: TWOD_DISPLAY
+                }]
+            )
+        );
+        //Leaving the display out is fine.
+        let header = "EDGE_WEIGHT_TYPE: EUC_2D\nDISPLAY_DATA_TYPE: NO_DISPLAY\n";
+        assert_eq!(parse(header, coords_2d), (TWOD_COORDS, NO_DISPLAY, vec![]));
+    }
+
+    #[test]
+    fn test_tours_span_lines() {
+        let input = "NAME: two
+TYPE: TOUR
+TOUR_SECTION
+1 2
+3 -1 3 2
+1 -1
+-1
+EOF
+";
+        let parsed = parse_str_with(input, &ParseOptions::default()).unwrap();
+        assert_eq!(parsed.problem.header.dimension, 3);
+        assert_eq!(
+            parsed.problem.data.tours,
+            Some(vec![vec![1, 2, 3], vec![3, 2, 1]])
+        );
+        assert_eq!(
+            parsed.diagnostics[0].kind,
+            DiagnosticKind::DefaultedField {
+                key: "DIMENSION".to_string(),
+                value: "3".to_string()
+            }
+        );
+
+        let input = "NAME: open\nTYPE: TOUR\nDIMENSION: 3\nTOUR_SECTION\n1 2 3\n";
+        let parsed = parse_str_with(input, &ParseOptions::default()).unwrap();
+        assert_eq!(parsed.problem.data.tours, Some(vec![vec![1, 2, 3]]));
+        assert_eq!(parsed.diagnostics[0].kind, DiagnosticKind::UnterminatedTour);
+        assert_eq!(
+            error_at("TYPE: TOUR\nDIMENSION: 3\nTOUR_SECTION\n1 2 -3 -1\n"),
+            (
+                4,
+                5,
+                Some("TOUR_SECTION".to_string()),
+                TsplibErrorKind::NotAnInteger("-3".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn test_strict() {
+        let strict = ParseOptions { strict: true };
+        let e =
+            parse_str_with("TYPE: TSP\nDIMENSION: 1\nDEMAND_SECTION\n1 -3\n", &strict).unwrap_err();
+        assert_eq!((e.line, e.column), (3, 1));
+        assert_eq!(
+            e.kind,
+            TsplibErrorKind::Strict(DiagnosticKind::DefaultedField {
+                key: "NAME".to_string(),
+                value: "".to_string()
+            })
+        );
+        let input =
+            "NAME: a\nTYPE: TSP\nDIMENSION: 1\nEDGE_WEIGHT_TYPE: EUC_2D\nVEHICLE_SECTION\n1\n";
+        let e = parse_str_with(input, &strict).unwrap_err();
+        assert_eq!((e.line, e.column), (5, 1));
+        assert_eq!(
+            e.kind,
+            TsplibErrorKind::Strict(DiagnosticKind::UnknownSection(
+                "VEHICLE_SECTION".to_string()
+            ))
+        );
+        assert!(parse_str(input).is_ok());
+    }
+
+    //Parses without the fast path of NODE_COORD_SECTION and EDGE_WEIGHT_SECTION.
+    fn parse_str_generic(input: &str) -> Result<TSPLProblem, TsplibError> {
+        let mut parser = Parser {
+            generic_rows: true,
+            ..Parser::default()
+        };
+        for line in input.lines() {
+            parser.line(line)?;
         }
+        parser.finish().map(|(problem, _)| problem)
     }
-}
 
-///Parse the contents of a file into an `Option<TSPLProblem>`
-pub fn parse_file(filename: &str) -> Option<TSPLProblem> {
-    fs::read_to_string(filename)
-        .ok()
-        .and_then(|input| parse_problem(&input).map(|x| x.1).ok())
+    //The rows of NODE_COORD_SECTION and EDGE_WEIGHT_SECTION take a shortcut, which has to give
+    //the same problems as the rows of every other section.
+    #[test]
+    fn fast_path_agrees_with_generic_rows() {
+        let files = [
+            "tests/testdata/berlin52.tsp",
+            "tests/testdata/bays29.tsp",             //FULL_MATRIX
+            "examples/alltsp/problems/si175.tsp",    //UPPER_DIAG_ROW
+            "examples/alltsp/problems/pa561.tsp",    //LOWER_DIAG_ROW
+            "examples/alltsp/problems/pla33810.tsp", //CEIL_2D
+            "examples/alltsp/problems/swiss42.tsp",  //trailing spaces
+            "examples/allatsp/rbg443.atsp",
+        ];
+        for path in files.iter() {
+            let contents = std::fs::read_to_string(path).unwrap();
+            let fast = parse_str(&contents).unwrap();
+            assert_eq!(Ok(&fast), parse_str_generic(&contents).as_ref(), "{}", path);
+            assert!(fast.data.node_coordinates.is_some() || fast.data.edge_weights.is_some());
+        }
+    }
+
+    #[test]
+    fn fast_path_errors_agree_with_generic_rows() {
+        let broken = [
+            "TYPE: TSP\nDIMENSION: 2\nNODE_COORD_SECTION\n1 0 0\n2 inf 1\n",
+            "TYPE: TSP\nDIMENSION: 2\nNODE_COORD_SECTION\n1 0 0\n2.0 1 1\n",
+            "TYPE: TSP\nDIMENSION: 2\nNODE_COORD_SECTION\n1 0 0\n2 1 1 1\n",
+            "TYPE: TSP\nDIMENSION: 2\nEDGE_WEIGHT_TYPE: EXPLICIT\nEDGE_WEIGHT_FORMAT: FULL_MATRIX\nEDGE_WEIGHT_SECTION\n0 1\n1 -2\n",
+            "TYPE: TSP\nDIMENSION: 2\nEDGE_WEIGHT_TYPE: EXPLICIT\nEDGE_WEIGHT_FORMAT: FULL_MATRIX\nEDGE_WEIGHT_SECTION\n0 1\n1 0x\n",
+        ];
+        for input in broken.iter() {
+            let e = parse_str(input).unwrap_err();
+            assert_eq!(Err(e), parse_str_generic(input), "{}", input);
+        }
+    }
 }
@@ -0,0 +1,457 @@
+use crate::distance::{coord_distance, geo_radians, xyz, GEO_RRR};
+use crate::enums::*;
+use crate::oracle::*;
+use noisy_float::prelude::*;
+use std::collections::BinaryHeap;
+
+///The candidate neighbors of `node`, with their distances, closest first.
+///Nodes are indexed from 0.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CandidateSet {
+    pub node: usize,
+    pub candidates: Vec<(usize, EdgeWeight)>,
+}
+
+//Subtrees with at most this many nodes are scanned instead of split further.
+const BUCKET: usize = 8;
+
+type Point = [f64; 3];
+
+//Where the tree puts a node. For GEO that is the node's position on the unit sphere,
+//so that the straight line distance between two points grows with the distance along
+//the sphere. The other metrics use the coordinates as they are.
+fn embed(ewt: &EdgeWeightType, c: &Coord) -> Point {
+    let (x, y, z) = xyz(c);
+    match ewt {
+        EdgeWeightType::GEO => {
+            let (lat, lon) = (geo_radians(x), geo_radians(y));
+            [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+        }
+        _ => [x, y, z.unwrap_or(0.0)],
+    }
+}
+
+fn is_3d(ewt: &EdgeWeightType) -> bool {
+    matches!(
+        ewt,
+        EdgeWeightType::EUC_3D
+            | EdgeWeightType::MAN_3D
+            | EdgeWeightType::MAX_3D
+            | EdgeWeightType::XRAY1
+            | EdgeWeightType::XRAY2
+            | EdgeWeightType::GEO
+    )
+}
+
+//Sorts `order[lo..hi]` into a tree: the node in the middle splits the rest along the
+//axis where they are the most spread out, with the smaller ones before it and the
+//larger ones after it, and the same goes for both halves.
+fn build(
+    points: &[Point],
+    dims: usize,
+    order: &mut [usize],
+    axes: &mut [u8],
+    lo: usize,
+    hi: usize,
+) {
+    if hi - lo <= BUCKET {
+        return;
+    }
+    let spread = |axis: usize| {
+        let values = order[lo..hi].iter().map(|&v| points[v][axis]);
+        let max = values.clone().fold(-f64::INFINITY, f64::max);
+        let min = values.fold(f64::INFINITY, f64::min);
+        max - min
+    };
+    let axis = (0..dims)
+        .max_by(|&a, &b| spread(a).total_cmp(&spread(b)))
+        .unwrap();
+    let mid = (lo + hi) / 2;
+    order[lo..hi].select_nth_unstable_by(mid - lo, |&a, &b| {
+        points[a][axis].total_cmp(&points[b][axis])
+    });
+    axes[mid] = axis as u8;
+    build(points, dims, order, axes, lo, mid);
+    build(points, dims, order, axes, mid + 1, hi);
+}
+
+//The state of a k-nearest query: the k best nodes found so far, worst on top.
+struct Search<'s, K> {
+    node: usize,
+    k: usize,
+    keep: K,
+    best: BinaryHeap<(EdgeWeight, usize)>,
+    oracle: &'s CoordDistances<'s>,
+}
+
+impl<'s, K: Fn(usize) -> bool> Search<'s, K> {
+    fn offer(&mut self, v: usize) {
+        if v == self.node || !(self.keep)(v) {
+            return;
+        }
+        let found = (self.oracle.dist(self.node, v), v);
+        if self.best.len() < self.k {
+            self.best.push(found);
+        } else if self.best.peek().is_some_and(|&worst| found < worst) {
+            self.best.pop();
+            self.best.push(found);
+        }
+    }
+
+    //Whether a node at distance `bound` or more could still make it into the results.
+    //Ties go to the lowest index, so a node at the same distance as the worst one might.
+    fn wants(&self, bound: EdgeWeight) -> bool {
+        self.best.len() < self.k || self.best.peek().is_some_and(|&(worst, _)| bound <= worst)
+    }
+}
+
+///A KD-tree over the coordinates of a problem, to find the nodes closest to a node
+///without looking at all the others.
+///
+///Distances are measured with the problem's `EdgeWeightType`, and the results are
+///the same as comparing every pair of nodes, ties going to the lowest index.
+///Building the tree takes O(n log n), and a query usually takes O(k log n), so the
+///candidate sets of every node take about O(n log n) instead of the O(n²) of
+///comparing every pair, which is what makes them usable on instances like pla85900.
+///`GEO` nodes are put on a sphere, and for `XRAY1` and `XRAY2` the tree can't skip
+///nodes on the wrapping phi angle, so those queries are slower.
+pub struct KdTree<'a> {
+    oracle: CoordDistances<'a>,
+    ewt: EdgeWeightType,
+    coords: &'a [Coord],
+    dims: usize,
+    points: Vec<Point>,
+    //The nodes, sorted into a tree by `build`.
+    order: Vec<usize>,
+    //The axis the node at the same position in `order` splits its subtree on.
+    axes: Vec<u8>,
+}
+
+impl<'a> KdTree<'a> {
+    ///Checks that `ewt` can be computed on every pair of `coords`, like `CoordDistances::new`,
+    ///and builds the tree.
+    pub fn new(ewt: EdgeWeightType, coords: &'a [Coord]) -> Result<Self, OracleError> {
+        let oracle = CoordDistances::new(ewt.clone(), coords)?;
+        let dims = if is_3d(&ewt) { 3 } else { 2 };
+        let points: Vec<Point> = coords.iter().map(|c| embed(&ewt, c)).collect();
+        let mut order: Vec<usize> = (0..coords.len()).collect();
+        let mut axes = vec![0; coords.len()];
+        build(&points, dims, &mut order, &mut axes, 0, coords.len());
+        Ok(KdTree {
+            oracle,
+            ewt,
+            coords,
+            dims,
+            points,
+            order,
+            axes,
+        })
+    }
+
+    ///The number of nodes.
+    pub fn dimension(&self) -> usize {
+        self.coords.len()
+    }
+
+    //The smallest distance two nodes can have when their points are at least `gap` apart along `axis`.
+    fn lower_bound(&self, axis: usize, gap: f64) -> EdgeWeight {
+        match self.ewt {
+            EdgeWeightType::GEO => {
+                //The points are on the unit sphere, so the angle between them is at least the
+                //one of a chord of length `gap`. A little is taken off for rounding errors.
+                let angle = 2.0 * (gap / 2.0).min(1.0).asin();
+                (GEO_RRR * angle + 1.0 - 1e-3).max(0.0) as EdgeWeight
+            }
+            //phi wraps around, so a gap along it says nothing about the distance.
+            EdgeWeightType::XRAY1 | EdgeWeightType::XRAY2 if axis == 0 => 0,
+            _ => {
+                //The other metrics grow with the difference along each axis, so the
+                //closest two nodes can be is right across the gap.
+                let mut d = [n64(0.0); 3];
+                d[axis] = n64(gap);
+                let (origin, across) = if self.dims == 3 {
+                    (
+                        Coord::Coord3(0, n64(0.0), n64(0.0), n64(0.0)),
+                        Coord::Coord3(0, d[0], d[1], d[2]),
+                    )
+                } else {
+                    (
+                        Coord::Coord2(0, n64(0.0), n64(0.0)),
+                        Coord::Coord2(0, d[0], d[1]),
+                    )
+                };
+                coord_distance(&self.ewt, &origin, &across).unwrap_or(0)
+            }
+        }
+    }
+
+    //Visits the subtree in `order[lo..hi]`, whose points are all inside the box from
+    //`min` to `max`, skipping it when `inside` says no wanted node can be in the box.
+    fn visit<K, I>(
+        &self,
+        search: &mut Search<K>,
+        inside: &I,
+        lo: usize,
+        hi: usize,
+        min: Point,
+        max: Point,
+    ) where
+        K: Fn(usize) -> bool,
+        I: Fn(&Point, &Point) -> bool,
+    {
+        if !inside(&min, &max) {
+            return;
+        }
+        if hi - lo <= BUCKET {
+            for &v in &self.order[lo..hi] {
+                search.offer(v);
+            }
+            return;
+        }
+        let mid = (lo + hi) / 2;
+        let axis = self.axes[mid] as usize;
+        let split = self.points[self.order[mid]][axis];
+        search.offer(self.order[mid]);
+
+        let (mut below_max, mut above_min) = (max, min);
+        below_max[axis] = split;
+        above_min[axis] = split;
+        let below = (lo, mid, min, below_max);
+        let above = (mid + 1, hi, above_min, max);
+        let diff = self.points[search.node][axis] - split;
+        let (near, far) = if diff < 0.0 {
+            (below, above)
+        } else {
+            (above, below)
+        };
+        self.visit(search, inside, near.0, near.1, near.2, near.3);
+        if search.wants(self.lower_bound(axis, diff.abs())) {
+            self.visit(search, inside, far.0, far.1, far.2, far.3);
+        }
+    }
+
+    fn search<K, I>(&self, node: usize, k: usize, keep: K, inside: I) -> Vec<(usize, EdgeWeight)>
+    where
+        K: Fn(usize) -> bool,
+        I: Fn(&Point, &Point) -> bool,
+    {
+        let mut search = Search {
+            node,
+            k,
+            keep,
+            best: BinaryHeap::with_capacity(k + 1),
+            oracle: &self.oracle,
+        };
+        if k > 0 {
+            let n = self.dimension();
+            self.visit(
+                &mut search,
+                &inside,
+                0,
+                n,
+                [-f64::INFINITY; 3],
+                [f64::INFINITY; 3],
+            );
+        }
+        search
+            .best
+            .into_sorted_vec()
+            .into_iter()
+            .map(|(d, v)| (v, d))
+            .collect()
+    }
+
+    ///The `k` nodes closest to `node`.
+    pub fn nearest(&self, node: usize, k: usize) -> CandidateSet {
+        CandidateSet {
+            node,
+            candidates: self.search(node, k, |_| true, |_: &Point, _: &Point| true),
+        }
+    }
+
+    ///How many quadrants there are around a node: 4 for 2d coordinates (and `GEO`),
+    ///8 for 3d ones.
+    pub fn quadrant_count(&self) -> usize {
+        match self.ewt {
+            EdgeWeightType::GEO => 4,
+            _ => 1 << self.dims,
+        }
+    }
+
+    ///Which quadrant around `node` the node `v` is in. Bit `i` of the quadrant is set when
+    ///`v` is below `node` along coordinate `i`, so nodes on the same spot are in quadrant 0.
+    pub fn quadrant(&self, node: usize, v: usize) -> usize {
+        let (center, point) = (raw(&self.coords[node]), raw(&self.coords[v]));
+        let axes = self.quadrant_count().trailing_zeros() as usize;
+        (0..axes)
+            .filter(|&i| point[i] < center[i])
+            .map(|i| 1 << i)
+            .sum()
+    }
+
+    ///The `per_quadrant` nodes closest to `node` in each of the quadrants around it,
+    ///which keeps candidates on every side of nodes on the edge of a cluster.
+    ///Quadrants are taken on the coordinates of the file, eg latitude and longitude for `GEO`.
+    pub fn quadrant_neighbors(&self, node: usize, per_quadrant: usize) -> CandidateSet {
+        let axes = self.quadrant_count().trailing_zeros() as usize;
+        let center = self.points[node];
+        let mut candidates = vec![];
+        for q in 0..self.quadrant_count() {
+            let keep = |v: usize| self.quadrant(node, v) == q;
+            if self.ewt == EdgeWeightType::GEO {
+                //The tree is on the sphere, so its boxes say nothing about latitude and longitude.
+                candidates
+                    .extend(self.search(node, per_quadrant, keep, |_: &Point, _: &Point| true));
+            } else {
+                //A box can only hold nodes of the quadrant if it reaches the right side of the node
+                //along every axis.
+                let inside = |min: &Point, max: &Point| {
+                    (0..axes).all(|i| {
+                        if q & (1 << i) != 0 {
+                            min[i] < center[i]
+                        } else {
+                            max[i] >= center[i]
+                        }
+                    })
+                };
+                candidates.extend(self.search(node, per_quadrant, keep, inside));
+            }
+        }
+        candidates.sort_unstable_by_key(|&(v, d)| (d, v));
+        CandidateSet { node, candidates }
+    }
+
+    ///The `k` nearest nodes of every node.
+    pub fn nearest_candidates(&self, k: usize) -> Vec<CandidateSet> {
+        (0..self.dimension()).map(|v| self.nearest(v, k)).collect()
+    }
+
+    ///The `per_quadrant` nearest nodes in each quadrant of every node.
+    pub fn quadrant_candidates(&self, per_quadrant: usize) -> Vec<CandidateSet> {
+        (0..self.dimension())
+            .map(|v| self.quadrant_neighbors(v, per_quadrant))
+            .collect()
+    }
+}
+
+fn raw(c: &Coord) -> Point {
+    let (x, y, z) = xyz(c);
+    [x, y, z.unwrap_or(0.0)]
+}
+
+impl TSPLProblem {
+    ///Builds a `KdTree` over the node coordinates, measuring distances with the
+    ///problem's `EDGE_WEIGHT_TYPE`.
+    pub fn kd_tree(&self) -> Result<KdTree<'_>, OracleError> {
+        let coords = self
+            .data
+            .node_coordinates
+            .as_ref()
+            .ok_or(OracleError::MissingCoordinates)?;
+        KdTree::new(self.header.edge_weight_type.clone(), coords)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //A deterministic scatter of points, some of them on the same spot.
+    fn scatter(n: usize, three_d: bool) -> Vec<Coord> {
+        let mut state: u64 = 12345;
+        let mut next = || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            n64(((state >> 33) % 1000) as f64 / 3.0)
+        };
+        (0..n)
+            .map(|i| {
+                if three_d {
+                    Coord::Coord3(i as i64 + 1, next(), next(), next())
+                } else {
+                    Coord::Coord2(i as i64 + 1, next(), next())
+                }
+            })
+            .collect()
+    }
+
+    fn brute_force<K: Fn(usize) -> bool>(
+        oracle: &CoordDistances,
+        node: usize,
+        k: usize,
+        keep: K,
+    ) -> Vec<(usize, EdgeWeight)> {
+        let mut all: Vec<(EdgeWeight, usize)> = (0..oracle.dimension())
+            .filter(|&v| v != node && keep(v))
+            .map(|v| (oracle.dist(node, v), v))
+            .collect();
+        all.sort_unstable();
+        all.into_iter().take(k).map(|(d, v)| (v, d)).collect()
+    }
+
+    fn check(ewt: EdgeWeightType, coords: &[Coord]) {
+        let oracle = CoordDistances::new(ewt.clone(), coords).unwrap();
+        let tree = KdTree::new(ewt.clone(), coords).unwrap();
+        for node in 0..coords.len() {
+            assert_eq!(
+                tree.nearest(node, 6).candidates,
+                brute_force(&oracle, node, 6, |_| true),
+                "{} {}",
+                ewt,
+                node
+            );
+            let mut expected: Vec<(usize, EdgeWeight)> = (0..tree.quadrant_count())
+                .flat_map(|q| brute_force(&oracle, node, 2, |v| tree.quadrant(node, v) == q))
+                .collect();
+            expected.sort_unstable_by_key(|&(v, d)| (d, v));
+            assert_eq!(
+                tree.quadrant_neighbors(node, 2).candidates,
+                expected,
+                "{} {}",
+                ewt,
+                node
+            );
+        }
+    }
+
+    #[test]
+    fn test_2d_metrics() {
+        let coords = scatter(300, false);
+        for ewt in [
+            EdgeWeightType::EUC_2D,
+            EdgeWeightType::CEIL_2D,
+            EdgeWeightType::MAN_2D,
+            EdgeWeightType::MAX_2D,
+            EdgeWeightType::ATT,
+        ]
+        .iter()
+        {
+            check(ewt.clone(), &coords);
+        }
+    }
+
+    #[test]
+    fn test_3d_metrics() {
+        let coords = scatter(300, true);
+        for ewt in [
+            EdgeWeightType::EUC_3D,
+            EdgeWeightType::MAN_3D,
+            EdgeWeightType::MAX_3D,
+            EdgeWeightType::XRAY1,
+            EdgeWeightType::XRAY2,
+        ]
+        .iter()
+        {
+            check(ewt.clone(), &coords);
+        }
+    }
+
+    #[test]
+    fn test_few_nodes() {
+        let coords = scatter(3, false);
+        let tree = KdTree::new(EdgeWeightType::EUC_2D, &coords).unwrap();
+        assert_eq!(tree.nearest(0, 5).candidates.len(), 2);
+        assert!(tree.nearest(0, 0).candidates.is_empty());
+    }
+}
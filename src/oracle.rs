@@ -0,0 +1,300 @@
+use crate::build_matrix::MatrixError;
+use crate::distance::coord_distance;
+use crate::enums::*;
+use std::cell::{Cell, OnceCell};
+use std::error::Error;
+use std::fmt;
+
+///Answers distance queries between the nodes of a problem, whether the distances
+///are computed from coordinates or were given explicitly.
+///Nodes are indexed from 0 to `dimension() - 1`.
+pub trait DistanceOracle {
+    ///The number of nodes.
+    fn dimension(&self) -> usize;
+    ///The distance (or edge weight) going from node `i` to node `j`.
+    fn dist(&self, i: usize, j: usize) -> EdgeWeight;
+    ///Whether `dist(i, j) == dist(j, i)` for every pair of nodes.
+    fn is_symmetric(&self) -> bool;
+}
+
+impl<O: DistanceOracle + ?Sized> DistanceOracle for Box<O> {
+    fn dimension(&self) -> usize {
+        (**self).dimension()
+    }
+    fn dist(&self, i: usize, j: usize) -> EdgeWeight {
+        (**self).dist(i, j)
+    }
+    fn is_symmetric(&self) -> bool {
+        (**self).is_symmetric()
+    }
+}
+
+impl<O: DistanceOracle + ?Sized> DistanceOracle for &O {
+    fn dimension(&self) -> usize {
+        (**self).dimension()
+    }
+    fn dist(&self, i: usize, j: usize) -> EdgeWeight {
+        (**self).dist(i, j)
+    }
+    fn is_symmetric(&self) -> bool {
+        (**self).is_symmetric()
+    }
+}
+
+///Why a problem can't give out a `DistanceOracle`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum OracleError {
+    ///The `EdgeWeightType` has no distance function, eg `SPECIAL`.
+    NoDistanceFunction(EdgeWeightType),
+    ///The `EdgeWeightType` is computed from coordinates, but there is no `NODE_COORD_SECTION`.
+    MissingCoordinates,
+    ///A 3d `EdgeWeightType`, but some of the coordinates are 2d.
+    WrongCoordinates(EdgeWeightType),
+    ///The explicit edge weights could not be made into a matrix.
+    Matrix(MatrixError),
+}
+
+impl fmt::Display for OracleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OracleError::NoDistanceFunction(ewt) => {
+                write!(f, "EDGE_WEIGHT_TYPE {} has no distance function", ewt)
+            }
+            OracleError::MissingCoordinates => write!(f, "the problem has no NODE_COORD_SECTION"),
+            OracleError::WrongCoordinates(ewt) => {
+                write!(f, "EDGE_WEIGHT_TYPE {} needs 3d coordinates", ewt)
+            }
+            OracleError::Matrix(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for OracleError {}
+
+impl From<MatrixError> for OracleError {
+    fn from(e: MatrixError) -> Self {
+        OracleError::Matrix(e)
+    }
+}
+
+///Computes distances from node coordinates every time they are asked for.
+#[derive(Debug, Clone)]
+pub struct CoordDistances<'a> {
+    ewt: EdgeWeightType,
+    coords: &'a [Coord],
+}
+
+impl<'a> CoordDistances<'a> {
+    ///Checks that `ewt` can be computed on every pair of `coords`.
+    pub fn new(ewt: EdgeWeightType, coords: &'a [Coord]) -> Result<Self, OracleError> {
+        if let EdgeWeightType::EXPLICIT | EdgeWeightType::SPECIAL = ewt {
+            return Err(OracleError::NoDistanceFunction(ewt));
+        }
+        //Comparing a node with itself is enough to see if the metric works on its coordinates.
+        if coords.iter().any(|c| coord_distance(&ewt, c, c).is_none()) {
+            return Err(OracleError::WrongCoordinates(ewt));
+        }
+        Ok(CoordDistances { ewt, coords })
+    }
+}
+
+impl<'a> DistanceOracle for CoordDistances<'a> {
+    fn dimension(&self) -> usize {
+        self.coords.len()
+    }
+    fn dist(&self, i: usize, j: usize) -> EdgeWeight {
+        //new() made sure the metric works for all of the coordinates.
+        let d = coord_distance(&self.ewt, &self.coords[i], &self.coords[j]).unwrap();
+        //GEO puts a node at distance 1 from itself.
+        if i == j {
+            0
+        } else {
+            d
+        }
+    }
+    fn is_symmetric(&self) -> bool {
+        true
+    }
+}
+
+///Looks distances up in a full edge weight matrix.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MatrixDistances {
+    matrix: EdgeWeightMatrix,
+    symmetric: bool,
+}
+
+impl MatrixDistances {
+    pub fn new(matrix: EdgeWeightMatrix) -> Self {
+        let n = matrix.len();
+        let symmetric = (0..n).all(|r| (0..r).all(|c| matrix[r][c] == matrix[c][r]));
+        MatrixDistances { matrix, symmetric }
+    }
+
+    pub fn matrix(&self) -> &EdgeWeightMatrix {
+        &self.matrix
+    }
+}
+
+impl DistanceOracle for MatrixDistances {
+    fn dimension(&self) -> usize {
+        self.matrix.len()
+    }
+    fn dist(&self, i: usize, j: usize) -> EdgeWeight {
+        self.matrix[i][j]
+    }
+    fn is_symmetric(&self) -> bool {
+        self.symmetric
+    }
+}
+
+///Wraps another oracle and remembers every distance it has been asked for,
+///so each one is computed at most once.
+///The cache is kept by rows, which only take memory once a distance in them has been
+///asked for, at 4 bytes per pair. Symmetric oracles only cache one triangle of the matrix.
+pub struct CachedDistances<O> {
+    inner: O,
+    rows: Vec<OnceCell<Box<[Cell<EdgeWeight>]>>>,
+}
+
+//Marks a distance that hasn't been computed yet. A distance that really is this large
+//just gets computed every time.
+const NOT_COMPUTED: EdgeWeight = EdgeWeight::MAX;
+
+impl<O: DistanceOracle> CachedDistances<O> {
+    pub fn new(inner: O) -> Self {
+        let rows = (0..inner.dimension()).map(|_| OnceCell::new()).collect();
+        CachedDistances { inner, rows }
+    }
+
+    //The row and column of the cache that hold the distance from i to j.
+    fn position(&self, i: usize, j: usize) -> (usize, usize) {
+        if self.inner.is_symmetric() && i < j {
+            (j, i)
+        } else {
+            (i, j)
+        }
+    }
+
+    pub fn into_inner(self) -> O {
+        self.inner
+    }
+}
+
+impl<O: DistanceOracle> DistanceOracle for CachedDistances<O> {
+    fn dimension(&self) -> usize {
+        self.inner.dimension()
+    }
+    fn dist(&self, i: usize, j: usize) -> EdgeWeight {
+        let (r, c) = self.position(i, j);
+        let len = if self.inner.is_symmetric() {
+            r + 1
+        } else {
+            self.dimension()
+        };
+        let row = self.rows[r].get_or_init(|| vec![Cell::new(NOT_COMPUTED); len].into());
+        let cell = &row[c];
+        match cell.get() {
+            NOT_COMPUTED => {
+                let d = self.inner.dist(i, j);
+                cell.set(d);
+                d
+            }
+            d => d,
+        }
+    }
+    fn is_symmetric(&self) -> bool {
+        self.inner.is_symmetric()
+    }
+}
+
+impl TSPLProblem {
+    ///Gives the right `DistanceOracle` for the problem's `EDGE_WEIGHT_TYPE`:
+    ///a `MatrixDistances` for `EXPLICIT` problems, and a `CoordDistances` otherwise.
+    pub fn distance_oracle(
+        &self,
+    ) -> Result<Box<dyn DistanceOracle + Send + Sync + '_>, OracleError> {
+        match self.header.edge_weight_type {
+            EdgeWeightType::EXPLICIT => {
+                Ok(Box::new(MatrixDistances::new(self.edge_weight_matrix()?)))
+            }
+            EdgeWeightType::SPECIAL => Err(OracleError::NoDistanceFunction(
+                self.header.edge_weight_type.clone(),
+            )),
+            _ => {
+                let coords = self
+                    .data
+                    .node_coordinates
+                    .as_ref()
+                    .ok_or(OracleError::MissingCoordinates)?;
+                Ok(Box::new(CoordDistances::new(
+                    self.header.edge_weight_type.clone(),
+                    coords,
+                )?))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use noisy_float::prelude::*;
+    use std::cell::RefCell;
+
+    //Counts how many times dist() is called.
+    struct Counting {
+        calls: RefCell<usize>,
+    }
+    impl DistanceOracle for Counting {
+        fn dimension(&self) -> usize {
+            3
+        }
+        fn dist(&self, i: usize, j: usize) -> EdgeWeight {
+            *self.calls.borrow_mut() += 1;
+            (i + j) as EdgeWeight
+        }
+        fn is_symmetric(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_cached_computes_once() {
+        let cached = CachedDistances::new(Counting {
+            calls: RefCell::new(0),
+        });
+        assert_eq!(cached.dist(0, 2), 2);
+        assert_eq!(cached.dist(2, 0), 2);
+        assert_eq!(cached.dist(1, 1), 2);
+        assert_eq!(cached.dist(0, 2), 2);
+        //Row 0 was never asked for.
+        let rows: Vec<_> = cached
+            .rows
+            .iter()
+            .map(|row| row.get().map(|r| r.len()))
+            .collect();
+        assert_eq!(rows, [None, Some(2), Some(3)]);
+        assert_eq!(*cached.into_inner().calls.borrow(), 2);
+    }
+
+    #[test]
+    fn test_matrix_symmetry() {
+        assert!(MatrixDistances::new(vec![vec![0, 1], vec![1, 0]]).is_symmetric());
+        assert!(!MatrixDistances::new(vec![vec![0, 1], vec![2, 0]]).is_symmetric());
+    }
+
+    #[test]
+    fn test_coords_must_fit_the_metric() {
+        let coords = vec![Coord::Coord2(1, n64(0.0), n64(0.0))];
+        assert!(CoordDistances::new(EdgeWeightType::EUC_2D, &coords).is_ok());
+        assert_eq!(
+            CoordDistances::new(EdgeWeightType::EUC_3D, &coords).err(),
+            Some(OracleError::WrongCoordinates(EdgeWeightType::EUC_3D))
+        );
+        assert_eq!(
+            CoordDistances::new(EdgeWeightType::SPECIAL, &coords).err(),
+            Some(OracleError::NoDistanceFunction(EdgeWeightType::SPECIAL))
+        );
+    }
+}
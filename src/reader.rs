@@ -0,0 +1,51 @@
+use std::io::{self, BufRead};
+
+//Every gzip file starts with these two bytes.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+//Looks at the start of the input, without consuming it, to see if it is gzip compressed.
+fn is_gzip<R: BufRead>(reader: &mut R) -> io::Result<bool> {
+    Ok(reader.fill_buf()?.starts_with(&GZIP_MAGIC))
+}
+
+//Puts a decoder in front of the input if it is gzip compressed.
+#[cfg(feature = "gzip")]
+pub(crate) fn decompress<'a, R: BufRead + 'a>(mut reader: R) -> io::Result<Box<dyn BufRead + 'a>> {
+    use flate2::bufread::MultiGzDecoder;
+    use std::io::BufReader;
+
+    match is_gzip(&mut reader)? {
+        true => Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader)))),
+        false => Ok(Box::new(reader)),
+    }
+}
+
+//Without the gzip feature, compressed input can only be refused.
+#[cfg(not(feature = "gzip"))]
+pub(crate) fn decompress<'a, R: BufRead + 'a>(mut reader: R) -> io::Result<Box<dyn BufRead + 'a>> {
+    match is_gzip(&mut reader)? {
+        true => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the input is gzip compressed, which needs the gzip feature",
+        )),
+        false => Ok(Box::new(reader)),
+    }
+}
+
+//Reads the next line into `line`, without its line ending. Gives false at the end of the input.
+pub(crate) fn next_line<R: BufRead + ?Sized>(
+    reader: &mut R,
+    line: &mut String,
+) -> io::Result<bool> {
+    line.clear();
+    if reader.read_line(line)? == 0 {
+        return Ok(false);
+    }
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    Ok(true)
+}
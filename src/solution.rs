@@ -0,0 +1,246 @@
+use crate::enums::*;
+use crate::error::TsplibError;
+use crate::oracle::*;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+///Why a tour is not a solution of a problem.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum TourError {
+    ///The tour file could not be parsed.
+    Parse(TsplibError),
+    ///The tour file should hold exactly one tour, but has this many.
+    TourCount(usize),
+    ///The `DIMENSION` of the tour file is not the one of the problem.
+    DimensionMismatch { problem: usize, tour: usize },
+    ///The tour doesn't visit as many nodes as the problem has.
+    WrongLength { expected: usize, found: usize },
+    ///The tour visits a node that is not between 1 and `dimension`.
+    NodeOutOfRange { node: usize, dimension: usize },
+    ///The tour visits this node more than once.
+    RepeatedNode(usize),
+    ///An edge of the `FIXED_EDGES_SECTION` is not in the tour.
+    FixedEdgeMissing(Edge),
+    ///The length of the tour could not be computed.
+    Oracle(OracleError),
+}
+
+impl fmt::Display for TourError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TourError::Parse(e) => e.fmt(f),
+            TourError::TourCount(count) => {
+                write!(f, "expected a single tour, but there are {}", count)
+            }
+            TourError::DimensionMismatch { problem, tour } => write!(
+                f,
+                "the tour has DIMENSION {}, but the problem has {}",
+                tour, problem
+            ),
+            TourError::WrongLength { expected, found } => write!(
+                f,
+                "the tour visits {} nodes, but the problem has {}",
+                found, expected
+            ),
+            TourError::NodeOutOfRange { node, dimension } => write!(
+                f,
+                "the tour visits node {}, but the nodes go from 1 to {}",
+                node, dimension
+            ),
+            TourError::RepeatedNode(node) => {
+                write!(f, "the tour visits node {} more than once", node)
+            }
+            TourError::FixedEdgeMissing((from, to)) => {
+                write!(
+                    f,
+                    "the fixed edge from {} to {} is not in the tour",
+                    from, to
+                )
+            }
+            TourError::Oracle(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for TourError {}
+
+impl From<TsplibError> for TourError {
+    fn from(e: TsplibError) -> Self {
+        TourError::Parse(e)
+    }
+}
+
+impl From<OracleError> for TourError {
+    fn from(e: OracleError) -> Self {
+        TourError::Oracle(e)
+    }
+}
+
+///A tour that has been checked against its problem.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct VerifiedTour {
+    ///The nodes in the order of the tour, indexed from 0.
+    pub nodes: Tour,
+    ///The length of the tour under the problem's `EDGE_WEIGHT_TYPE`, back to where it started.
+    pub length: u64,
+}
+
+impl TSPLProblem {
+    ///Checks that `tour`, with the nodes counted from 1 like in a `TOUR_SECTION`, visits every
+    ///node of the problem once and uses every edge of the `FIXED_EDGES_SECTION`, and
+    ///computes its length. Fixed edges can be used in either direction, except for ATSP problems.
+    pub fn verify_tour(&self, tour: &[usize]) -> Result<VerifiedTour, TourError> {
+        let n = self.header.dimension as usize;
+        if tour.len() != n {
+            return Err(TourError::WrongLength {
+                expected: n,
+                found: tour.len(),
+            });
+        }
+        //Where each node is in the tour.
+        let mut position = vec![None; n];
+        for (i, &node) in tour.iter().enumerate() {
+            let slot = node
+                .checked_sub(1)
+                .and_then(|v| position.get_mut(v))
+                .ok_or(TourError::NodeOutOfRange { node, dimension: n })?;
+            if slot.replace(i).is_some() {
+                return Err(TourError::RepeatedNode(node));
+            }
+        }
+        let nodes: Tour = tour.iter().map(|&node| node - 1).collect();
+
+        let directed = self.header.problem_type == ProblemType::ATSP;
+        let follows = |a: usize, b: usize| nodes[(position[a].unwrap() + 1) % n] == b;
+        for &(from, to) in self.data.fixed_edges.iter().flatten() {
+            let in_tour = (1..=n).contains(&from)
+                && (1..=n).contains(&to)
+                && (follows(from - 1, to - 1) || (!directed && follows(to - 1, from - 1)));
+            if !in_tour {
+                return Err(TourError::FixedEdgeMissing((from, to)));
+            }
+        }
+
+        let oracle = self.distance_oracle()?;
+        let length = nodes
+            .iter()
+            .zip(nodes.iter().cycle().skip(1))
+            .map(|(&a, &b)| u64::from(oracle.dist(a, b)))
+            .sum();
+        Ok(VerifiedTour { nodes, length })
+    }
+
+    ///Checks the single tour of a parsed `TOUR` file against this problem, see `verify_tour`.
+    pub fn load_tour(&self, tour: &TSPLProblem) -> Result<VerifiedTour, TourError> {
+        if tour.header.dimension != self.header.dimension {
+            return Err(TourError::DimensionMismatch {
+                problem: self.header.dimension as usize,
+                tour: tour.header.dimension as usize,
+            });
+        }
+        match tour.data.tours.as_deref() {
+            Some([nodes]) => self.verify_tour(nodes),
+            tours => Err(TourError::TourCount(tours.map_or(0, |t| t.len()))),
+        }
+    }
+
+    ///Parses a `TOUR` file and checks its tour against this problem, see `verify_tour`.
+    pub fn load_tour_path<P: AsRef<Path>>(&self, path: P) -> Result<VerifiedTour, TourError> {
+        self.load_tour(&crate::parse_path(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_str;
+
+    //Four nodes on the corners of a 3 by 4 rectangle.
+    fn rectangle() -> TSPLProblem {
+        parse_str(
+            "NAME: rectangle
+TYPE: TSP
+DIMENSION: 4
+EDGE_WEIGHT_TYPE: EUC_2D
+NODE_COORD_SECTION
+1 0 0
+2 3 0
+3 3 4
+4 0 4
+FIXED_EDGES_SECTION
+3 2
+-1
+EOF
+",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_verify_tour() {
+        let p = rectangle();
+        assert_eq!(
+            p.verify_tour(&[1, 2, 3, 4]),
+            Ok(VerifiedTour {
+                nodes: vec![0, 1, 2, 3],
+                length: 14
+            })
+        );
+        assert_eq!(p.verify_tour(&[4, 1, 3, 2]).unwrap().length, 18);
+        assert_eq!(
+            p.verify_tour(&[1, 2, 3]),
+            Err(TourError::WrongLength {
+                expected: 4,
+                found: 3
+            })
+        );
+        assert_eq!(
+            p.verify_tour(&[1, 2, 3, 5]),
+            Err(TourError::NodeOutOfRange {
+                node: 5,
+                dimension: 4
+            })
+        );
+        assert_eq!(
+            p.verify_tour(&[1, 2, 3, 0]),
+            Err(TourError::NodeOutOfRange {
+                node: 0,
+                dimension: 4
+            })
+        );
+        assert_eq!(
+            p.verify_tour(&[1, 2, 3, 2]),
+            Err(TourError::RepeatedNode(2))
+        );
+        assert_eq!(
+            p.verify_tour(&[1, 3, 4, 2]),
+            Err(TourError::FixedEdgeMissing((3, 2)))
+        );
+    }
+
+    #[test]
+    fn test_load_tour() {
+        let p = rectangle();
+        let tour = |dimension: usize, section: &str| {
+            parse_str(&format!(
+                "TYPE: TOUR\nDIMENSION: {}\nTOUR_SECTION\n{}\nEOF\n",
+                dimension, section
+            ))
+            .unwrap()
+        };
+        assert_eq!(p.load_tour(&tour(4, "4 3\n2 1\n-1")).unwrap().length, 14);
+        assert_eq!(
+            p.load_tour(&tour(5, "4 3 2 1 5 -1")),
+            Err(TourError::DimensionMismatch {
+                problem: 4,
+                tour: 5
+            })
+        );
+        assert_eq!(
+            p.load_tour(&tour(4, "4 3 2 1 -1 1 2 3 4 -1")),
+            Err(TourError::TourCount(2))
+        );
+        assert_eq!(p.load_tour(&tour(4, "-1")), Err(TourError::TourCount(0)));
+    }
+}
@@ -0,0 +1,262 @@
+use crate::build_matrix::*;
+use crate::enums::*;
+use crate::oracle::*;
+use std::error::Error;
+use std::fmt;
+
+///Why a problem could not be transformed into another representation.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum TransformError {
+    ///The explicit edge weights of the problem could not be made into a matrix.
+    Matrix(MatrixError),
+    ///The distances of the problem could not be computed.
+    Oracle(OracleError),
+    ///The target `EdgeWeightFormat` is not a matrix layout, eg `FUNCTION`.
+    NotALayout(EdgeWeightFormat),
+    ///The target format only stores one triangle, but the weights from `row` to `column`
+    ///and from `column` to `row` differ.
+    Asymmetric {
+        format: EdgeWeightFormat,
+        row: usize,
+        column: usize,
+    },
+    ///The target format doesn't store the diagonal, but the weight of `node` to itself isn't 0.
+    NonZeroDiagonal {
+        format: EdgeWeightFormat,
+        node: usize,
+        weight: EdgeWeight,
+    },
+    ///The weights are too large to add the big M of a transformation to them.
+    WeightOverflow,
+    ///The tour doesn't visit as many nodes as the problem has.
+    WrongTourLength { expected: usize, found: usize },
+    ///The tour was not made on a transformed problem: `node` is not next to its ghost node,
+    ///or is not visited exactly once.
+    NotATransformedTour { node: usize },
+}
+
+impl fmt::Display for TransformError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TransformError::Matrix(e) => e.fmt(f),
+            TransformError::Oracle(e) => e.fmt(f),
+            TransformError::NotALayout(ewf) => write!(f, "{} is not a matrix layout", ewf),
+            TransformError::Asymmetric {
+                format,
+                row,
+                column,
+            } => write!(
+                f,
+                "{} can only hold symmetric weights, but ({}, {}) and ({}, {}) differ",
+                format, row, column, column, row
+            ),
+            TransformError::NonZeroDiagonal {
+                format,
+                node,
+                weight,
+            } => write!(
+                f,
+                "{} has no diagonal, but node {} has weight {} to itself",
+                format, node, weight
+            ),
+            TransformError::WeightOverflow => {
+                write!(f, "the weights are too large to be transformed")
+            }
+            TransformError::WrongTourLength { expected, found } => write!(
+                f,
+                "the tour should visit {} nodes, but visits {}",
+                expected, found
+            ),
+            TransformError::NotATransformedTour { node } => write!(
+                f,
+                "node {} is not visited right before or after its ghost node",
+                node
+            ),
+        }
+    }
+}
+
+impl Error for TransformError {}
+
+impl From<MatrixError> for TransformError {
+    fn from(e: MatrixError) -> Self {
+        TransformError::Matrix(e)
+    }
+}
+
+impl From<OracleError> for TransformError {
+    fn from(e: OracleError) -> Self {
+        TransformError::Oracle(e)
+    }
+}
+
+fn has_diagonal(ewf: &EdgeWeightFormat) -> bool {
+    match ewf {
+        EdgeWeightFormat::FULL_MATRIX
+        | EdgeWeightFormat::UPPER_DIAG_ROW
+        | EdgeWeightFormat::LOWER_DIAG_ROW
+        | EdgeWeightFormat::UPPER_DIAG_COL
+        | EdgeWeightFormat::LOWER_DIAG_COL => true,
+        EdgeWeightFormat::FUNCTION
+        | EdgeWeightFormat::UPPER_ROW
+        | EdgeWeightFormat::LOWER_ROW
+        | EdgeWeightFormat::UPPER_COL
+        | EdgeWeightFormat::LOWER_COL => false,
+    }
+}
+
+//Writes the weights in the `ewf` layout, asking `dist` for each of them.
+pub(crate) fn encode<F>(
+    n: usize,
+    dist: F,
+    ewf: &EdgeWeightFormat,
+) -> Result<EdgeWeightList, TransformError>
+where
+    F: Fn(usize, usize) -> EdgeWeight,
+{
+    let positions = positions(ewf, n).ok_or_else(|| TransformError::NotALayout(ewf.clone()))?;
+    if *ewf != EdgeWeightFormat::FULL_MATRIX {
+        for row in 0..n {
+            if let Some(column) = (0..row).find(|&c| dist(row, c) != dist(c, row)) {
+                return Err(TransformError::Asymmetric {
+                    format: ewf.clone(),
+                    row,
+                    column,
+                });
+            }
+        }
+    }
+    if !has_diagonal(ewf) {
+        if let Some(node) = (0..n).find(|&i| dist(i, i) != 0) {
+            return Err(TransformError::NonZeroDiagonal {
+                format: ewf.clone(),
+                node,
+                weight: dist(node, node),
+            });
+        }
+    }
+    Ok(positions.map(|(r, c)| dist(r, c)).collect())
+}
+
+///Writes a full matrix out as the weights of an `EDGE_WEIGHT_SECTION` in the `ewf` layout.
+///This is the reverse of `build_distance_matrix`, and refuses to drop any information:
+///asymmetric matrices only fit in `FULL_MATRIX`, and the diagonal has to be 0 for the
+///layouts that leave it out.
+pub fn encode_matrix(
+    matrix: &[EdgeWeightList],
+    ewf: &EdgeWeightFormat,
+) -> Result<EdgeWeightList, TransformError> {
+    encode(matrix.len(), |r, c| matrix[r][c], ewf)
+}
+
+///Same as `encode_matrix`, but asks an oracle for the weights instead, so the full
+///matrix never has to be in memory.
+pub fn encode_oracle<O: DistanceOracle>(
+    oracle: &O,
+    ewf: &EdgeWeightFormat,
+) -> Result<EdgeWeightList, TransformError> {
+    encode(oracle.dimension(), |r, c| oracle.dist(r, c), ewf)
+}
+
+impl TSPLProblem {
+    ///Rewrites the `EDGE_WEIGHT_SECTION` of an explicit problem in another layout,
+    ///and updates `EDGE_WEIGHT_FORMAT` to match.
+    ///The problem is left untouched when the conversion would lose weights.
+    pub fn convert_edge_weight_format(
+        &mut self,
+        ewf: EdgeWeightFormat,
+    ) -> Result<(), TransformError> {
+        let matrix = self.edge_weight_matrix()?;
+        self.data.edge_weights = Some(encode_matrix(&matrix, &ewf)?);
+        self.header.edge_weight_format = Some(ewf);
+        Ok(())
+    }
+
+    ///Computes every distance of a coordinate based problem, and gives back the same
+    ///problem as an `EXPLICIT` one with its weights in the `ewf` layout.
+    ///The 2d node coordinates become the `DISPLAY_DATA_SECTION`, unless the problem
+    ///already has one, so the nodes can still be drawn. 3d coordinates are dropped.
+    ///Explicit problems are converted to the `ewf` layout, and refused like in
+    ///`convert_edge_weight_format` when it can't hold their weights.
+    pub fn to_explicit(&self, ewf: EdgeWeightFormat) -> Result<TSPLProblem, TransformError> {
+        let weights = encode_oracle(&self.distance_oracle()?, &ewf)?;
+        let mut res = self.clone();
+        if let Some(coords) = res.data.node_coordinates.take() {
+            let is_2d = coords.iter().all(|c| matches!(c, Coord::Coord2(..)));
+            if res.data.display_data.is_none() && is_2d {
+                res.data.display_data = Some(coords);
+                res.header.display_data_type = DisplayDataType::TWOD_DISPLAY;
+            } else if res.data.display_data.is_none() {
+                res.header.display_data_type = DisplayDataType::NO_DISPLAY;
+            }
+        }
+        res.header.edge_weight_type = EdgeWeightType::EXPLICIT;
+        res.header.edge_weight_format = Some(ewf);
+        res.header.node_coord_type = NodeCoordType::NO_COORDS;
+        res.data.edge_weights = Some(weights);
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_is_the_reverse_of_build() {
+        let matrix = vec![
+            vec![0, 1, 2, 3],
+            vec![1, 0, 4, 5],
+            vec![2, 4, 0, 6],
+            vec![3, 5, 6, 0],
+        ];
+        for ewf in [
+            EdgeWeightFormat::FULL_MATRIX,
+            EdgeWeightFormat::UPPER_ROW,
+            EdgeWeightFormat::LOWER_ROW,
+            EdgeWeightFormat::UPPER_DIAG_ROW,
+            EdgeWeightFormat::LOWER_DIAG_ROW,
+            EdgeWeightFormat::UPPER_COL,
+            EdgeWeightFormat::LOWER_COL,
+            EdgeWeightFormat::UPPER_DIAG_COL,
+            EdgeWeightFormat::LOWER_DIAG_COL,
+        ]
+        .iter()
+        {
+            let weights = encode_matrix(&matrix, ewf).unwrap();
+            assert_eq!(weights.len(), entry_count(ewf, 4).unwrap());
+            assert_eq!(build_distance_matrix(4, ewf, &weights), Ok(matrix.clone()));
+        }
+        assert_eq!(
+            encode_matrix(&matrix, &EdgeWeightFormat::UPPER_ROW),
+            Ok(vec![1, 2, 3, 4, 5, 6])
+        );
+    }
+
+    #[test]
+    fn test_lossy_conversions() {
+        let asym = vec![vec![0, 1], vec![2, 0]];
+        assert_eq!(
+            encode_matrix(&asym, &EdgeWeightFormat::LOWER_DIAG_ROW),
+            Err(TransformError::Asymmetric {
+                format: EdgeWeightFormat::LOWER_DIAG_ROW,
+                row: 1,
+                column: 0,
+            })
+        );
+        let diag = vec![vec![9, 1], vec![1, 9]];
+        assert!(encode_matrix(&diag, &EdgeWeightFormat::UPPER_DIAG_ROW).is_ok());
+        assert_eq!(
+            encode_matrix(&diag, &EdgeWeightFormat::UPPER_ROW),
+            Err(TransformError::NonZeroDiagonal {
+                format: EdgeWeightFormat::UPPER_ROW,
+                node: 0,
+                weight: 9,
+            })
+        );
+        assert_eq!(
+            encode_matrix(&diag, &EdgeWeightFormat::FUNCTION),
+            Err(TransformError::NotALayout(EdgeWeightFormat::FUNCTION))
+        );
+    }
+}
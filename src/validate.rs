@@ -0,0 +1,390 @@
+use crate::enums::*;
+use std::error::Error;
+use std::fmt;
+
+///A way in which the data sections of a problem don't match each other or its header.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ValidationError {
+    ///A section that has a row per node doesn't have `dimension` rows.
+    WrongCount {
+        section: String,
+        expected: usize,
+        found: usize,
+    },
+    ///A node id that is not between 1 and `dimension`.
+    NodeOutOfRange {
+        section: String,
+        node: i64,
+        dimension: usize,
+    },
+    ///A node id that shows up twice in a section where it should be given once.
+    DuplicateNode { section: String, node: i64 },
+    ///The coordinates of a node don't work with the `EDGE_WEIGHT_TYPE`, eg 2d coordinates
+    ///for `EUC_3D`.
+    WrongCoordinates {
+        node: i64,
+        edge_weight_type: EdgeWeightType,
+    },
+    ///`NODE_COORD_TYPE` says 2d coordinates but `EDGE_WEIGHT_TYPE` needs 3d ones, or the other way around.
+    CoordTypeMismatch {
+        edge_weight_type: EdgeWeightType,
+        node_coord_type: NodeCoordType,
+    },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationError::WrongCount {
+                section,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} has {} rows, but DIMENSION is {}",
+                section, found, expected
+            ),
+            ValidationError::NodeOutOfRange {
+                section,
+                node,
+                dimension,
+            } => write!(
+                f,
+                "{} has node {}, but the nodes go from 1 to {}",
+                section, node, dimension
+            ),
+            ValidationError::DuplicateNode { section, node } => {
+                write!(f, "{} has node {} more than once", section, node)
+            }
+            ValidationError::WrongCoordinates {
+                node,
+                edge_weight_type,
+            } => write!(
+                f,
+                "the coordinates of node {} don't work with EDGE_WEIGHT_TYPE {}",
+                node, edge_weight_type
+            ),
+            ValidationError::CoordTypeMismatch {
+                edge_weight_type,
+                node_coord_type,
+            } => write!(
+                f,
+                "EDGE_WEIGHT_TYPE {} can't be used with NODE_COORD_TYPE {}",
+                edge_weight_type, node_coord_type
+            ),
+        }
+    }
+}
+
+impl Error for ValidationError {}
+
+impl ValidationError {
+    //The section the error is in, or the header keyword it is about.
+    pub(crate) fn context(&self) -> &str {
+        match self {
+            ValidationError::WrongCount { section, .. }
+            | ValidationError::NodeOutOfRange { section, .. }
+            | ValidationError::DuplicateNode { section, .. } => section,
+            ValidationError::WrongCoordinates { .. } => "NODE_COORD_SECTION",
+            ValidationError::CoordTypeMismatch { .. } => "NODE_COORD_TYPE",
+        }
+    }
+}
+
+//The metrics that need a third coordinate.
+fn is_3d(ewt: &EdgeWeightType) -> bool {
+    ewt.node_coord_type() == Some(NodeCoordType::THREED_COORDS)
+}
+
+fn coord_id(c: &Coord) -> i64 {
+    match c {
+        Coord::Coord2(id, ..) | Coord::Coord3(id, ..) => *id,
+    }
+}
+
+//Checks that every id is a node, and when `once`, that no node is given twice.
+pub(crate) fn check_ids<I: IntoIterator<Item = i64>>(
+    section: &str,
+    ids: I,
+    dimension: usize,
+    once: bool,
+    errors: &mut Vec<ValidationError>,
+) {
+    let mut seen = vec![false; dimension];
+    for node in ids {
+        if node < 1 || node as usize > dimension {
+            errors.push(ValidationError::NodeOutOfRange {
+                section: section.to_string(),
+                node,
+                dimension,
+            });
+        } else if std::mem::replace(&mut seen[node as usize - 1], true) && once {
+            errors.push(ValidationError::DuplicateNode {
+                section: section.to_string(),
+                node,
+            });
+        }
+    }
+}
+
+//Checks that a section with a row per node has `dimension` rows.
+pub(crate) fn check_count(
+    section: &str,
+    found: usize,
+    dimension: usize,
+    errors: &mut Vec<ValidationError>,
+) {
+    if found != dimension {
+        errors.push(ValidationError::WrongCount {
+            section: section.to_string(),
+            expected: dimension,
+            found,
+        });
+    }
+}
+
+impl TSPLProblem {
+    ///Checks the data sections against `DIMENSION` and each other: every section with a row
+    ///per node has one for each node, node ids go from 1 to `DIMENSION` and aren't repeated,
+    ///depots and fixed edges are nodes, and the coordinates work with the `EDGE_WEIGHT_TYPE`.
+    ///
+    ///Gives back everything that is wrong, not just the first thing.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let header = &self.header;
+        let data = &self.data;
+        let n = header.dimension as usize;
+        let ewt = &header.edge_weight_type;
+        let mut errors = vec![];
+
+        for (section, coords) in [
+            ("NODE_COORD_SECTION", &data.node_coordinates),
+            ("DISPLAY_DATA_SECTION", &data.display_data),
+        ]
+        .iter()
+        {
+            if let Some(coords) = coords {
+                check_ids(section, coords.iter().map(coord_id), n, true, &mut errors);
+                check_count(section, coords.len(), n, &mut errors);
+            }
+        }
+        if let Some(depots) = &data.depots {
+            check_ids(
+                "DEPOT_SECTION",
+                depots.iter().map(|&d| d as i64),
+                n,
+                true,
+                &mut errors,
+            );
+        }
+        if let Some(demands) = &data.demands {
+            check_count("DEMAND_SECTION", demands.len(), n, &mut errors);
+        }
+        if let Some(edges) = &data.edges {
+            let ends = edges.iter().flat_map(|edge| match edge {
+                EdgeData::Edge((a, b)) => vec![*a as i64, *b as i64],
+                EdgeData::Adj(adj) => adj.iter().map(|&b| b as i64).collect(),
+            });
+            check_ids("EDGE_DATA_SECTION", ends, n, false, &mut errors);
+        }
+        if let Some(fixed) = &data.fixed_edges {
+            let ends = fixed.iter().flat_map(|&(a, b)| vec![a as i64, b as i64]);
+            check_ids("FIXED_EDGES_SECTION", ends, n, false, &mut errors);
+        }
+
+        let coord_type_fits = match header.node_coord_type {
+            NodeCoordType::TWOD_COORDS => !is_3d(ewt),
+            NodeCoordType::THREED_COORDS => is_3d(ewt) || *ewt == EdgeWeightType::EXPLICIT,
+            NodeCoordType::NO_COORDS => true,
+        };
+        if !coord_type_fits {
+            errors.push(ValidationError::CoordTypeMismatch {
+                edge_weight_type: ewt.clone(),
+                node_coord_type: header.node_coord_type.clone(),
+            });
+        }
+        if is_3d(ewt) {
+            let flat = data
+                .node_coordinates
+                .iter()
+                .flatten()
+                .filter(|c| matches!(c, Coord::Coord2(..)));
+            errors.extend(flat.map(|c| ValidationError::WrongCoordinates {
+                node: coord_id(c),
+                edge_weight_type: ewt.clone(),
+            }));
+        }
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_str;
+
+    fn errors(input: &str) -> Vec<ValidationError> {
+        match parse_str(input).unwrap_err().kind {
+            crate::TsplibErrorKind::Invalid(errors) => errors,
+            kind => panic!("not a validation error: {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_node_ids() {
+        let input = "TYPE: CVRP
+DIMENSION: 3
+EDGE_DATA_FORMAT: EDGE_LIST
+NODE_COORD_SECTION
+1 0 0
+3 1 1
+3 2 2
+DEPOT_SECTION
+4
+-1
+EDGE_DATA_SECTION
+1 2
+3 9
+-1
+FIXED_EDGES_SECTION
+1 0
+";
+        let section = |s: &str| s.to_string();
+        assert_eq!(
+            errors(input),
+            vec![
+                ValidationError::DuplicateNode {
+                    section: section("NODE_COORD_SECTION"),
+                    node: 3
+                },
+                ValidationError::NodeOutOfRange {
+                    section: section("DEPOT_SECTION"),
+                    node: 4,
+                    dimension: 3
+                },
+                ValidationError::NodeOutOfRange {
+                    section: section("EDGE_DATA_SECTION"),
+                    node: 9,
+                    dimension: 3
+                },
+                ValidationError::NodeOutOfRange {
+                    section: section("FIXED_EDGES_SECTION"),
+                    node: 0,
+                    dimension: 3
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_adjacency_ids() {
+        let adjacencies = |dimension: usize, rows: &str| {
+            errors(&format!(
+                "TYPE: TSP
+DIMENSION: {}
+EDGE_DATA_FORMAT: ADJ_LIST
+EDGE_DATA_SECTION
+{}
+-1
+",
+                dimension, rows
+            ))
+        };
+        let out_of_range = |node: i64, dimension: usize| ValidationError::NodeOutOfRange {
+            section: "EDGE_DATA_SECTION".to_string(),
+            node,
+            dimension,
+        };
+        assert_eq!(adjacencies(2, "5 1 -1"), vec![out_of_range(5, 2)]);
+        assert_eq!(adjacencies(2, "3 1 -1"), vec![out_of_range(3, 2)]);
+        assert_eq!(adjacencies(2, "0 1 -1"), vec![out_of_range(0, 2)]);
+        assert_eq!(adjacencies(0, "1 2 -1"), vec![out_of_range(1, 0)]);
+        assert_eq!(adjacencies(2, "1 9 -1"), vec![out_of_range(9, 2)]);
+        //A node's adjacency list is given once.
+        assert_eq!(
+            adjacencies(3, "1 2 -1\n2 3 -1\n1 3 -1"),
+            vec![ValidationError::DuplicateNode {
+                section: "EDGE_DATA_SECTION".to_string(),
+                node: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn test_node_counts() {
+        let input = "TYPE: CVRP
+DIMENSION: 3
+EDGE_WEIGHT_TYPE: EXPLICIT
+EDGE_WEIGHT_FORMAT: UPPER_ROW
+EDGE_WEIGHT_SECTION
+1 2 3
+DISPLAY_DATA_SECTION
+1 0 0
+2 1 1
+DEMAND_SECTION
+1 5
+3 5
+3 6
+4 1
+";
+        let section = |s: &str| s.to_string();
+        assert_eq!(
+            errors(input),
+            vec![
+                ValidationError::DuplicateNode {
+                    section: section("DEMAND_SECTION"),
+                    node: 3
+                },
+                ValidationError::NodeOutOfRange {
+                    section: section("DEMAND_SECTION"),
+                    node: 4,
+                    dimension: 3
+                },
+                ValidationError::WrongCount {
+                    section: section("DEMAND_SECTION"),
+                    expected: 3,
+                    found: 4
+                },
+                ValidationError::WrongCount {
+                    section: section("DISPLAY_DATA_SECTION"),
+                    expected: 3,
+                    found: 2
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_header_consistency() {
+        let input = "TYPE: TSP
+DIMENSION: 2
+EDGE_WEIGHT_TYPE: EUC_3D
+NODE_COORD_TYPE: TWOD_COORDS
+NODE_COORD_SECTION
+1 0 0
+2 1 1
+";
+        assert_eq!(
+            errors(input),
+            vec![
+                ValidationError::CoordTypeMismatch {
+                    edge_weight_type: EdgeWeightType::EUC_3D,
+                    node_coord_type: NodeCoordType::TWOD_COORDS
+                },
+                ValidationError::WrongCoordinates {
+                    node: 1,
+                    edge_weight_type: EdgeWeightType::EUC_3D
+                },
+                ValidationError::WrongCoordinates {
+                    node: 2,
+                    edge_weight_type: EdgeWeightType::EUC_3D
+                },
+            ]
+        );
+        //There's no section to point at for the header, so the error names the keyword.
+        let e = parse_str(input).unwrap_err();
+        assert_eq!((e.line, e.context.as_deref()), (0, Some("NODE_COORD_TYPE")));
+    }
+}
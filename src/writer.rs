@@ -0,0 +1,576 @@
+use crate::build_matrix::{entry_count, positions};
+use crate::enums::*;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+///How `write_problem` formats the numbers of the data sections.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct WriteOptions {
+    ///Digits after the decimal point of coordinates. `None` writes the shortest number that
+    ///reads back as the same one.
+    pub precision: Option<usize>,
+    ///Numbers are right-aligned to this many characters, so that the columns line up like
+    ///in the TSPLIB files. 0 doesn't pad them.
+    pub width: usize,
+}
+
+impl WriteOptions {
+    fn pad(&self, number: &str) -> String {
+        format!("{:>1$}", number, self.width)
+    }
+
+    fn real(&self, x: f64) -> String {
+        match self.precision {
+            Some(precision) => self.pad(&format!("{:.1$}", x, precision)),
+            None => self.pad(&x.to_string()),
+        }
+    }
+}
+
+//Writes one row of a section, with the numbers separated by a space.
+fn row<W: Write>(out: &mut W, numbers: &[String]) -> io::Result<()> {
+    writeln!(out, "{}", numbers.join(" "))
+}
+
+//Nodes of a tour go this many to a line in the TOUR_SECTION of a problem.
+const TOUR_NODES_PER_LINE: usize = 16;
+
+//Writes the nodes of a tour `per_line` to a line, or all on one line for 0. When there is more
+//than one to a line, they are padded to line up, and to at least `width` in any case.
+fn write_nodes<W: Write>(
+    out: &mut W,
+    nodes: &[usize],
+    per_line: usize,
+    width: usize,
+) -> io::Result<()> {
+    let per_line = match per_line {
+        0 => nodes.len().max(1),
+        per_line => per_line,
+    };
+    let widest = nodes.iter().max().map_or(0, |node| node.to_string().len());
+    let padding = WriteOptions {
+        width: if per_line > 1 {
+            width.max(widest)
+        } else {
+            width
+        },
+        ..WriteOptions::default()
+    };
+    for line in nodes.chunks(per_line) {
+        let numbers: Vec<_> = line
+            .iter()
+            .map(|node| padding.pad(&node.to_string()))
+            .collect();
+        row(out, &numbers)?;
+    }
+    Ok(())
+}
+
+fn field<W: Write, T: fmt::Display>(out: &mut W, key: &str, value: T) -> io::Result<()> {
+    let value = value.to_string();
+    match value.is_empty() {
+        true => writeln!(out, "{}:", key),
+        false => writeln!(out, "{}: {}", key, value),
+    }
+}
+
+fn write_header<W: Write>(out: &mut W, header: &TSPLMeta, data: &TSPLData) -> io::Result<()> {
+    if !header.name.is_empty() {
+        field(out, "NAME", &header.name)?;
+    }
+    field(out, "TYPE", &header.problem_type)?;
+    if !header.comment.is_empty() {
+        field(out, "COMMENT", &header.comment)?;
+    }
+    field(out, "DIMENSION", header.dimension)?;
+    if let Some(capacity) = header.capacity {
+        field(out, "CAPACITY", capacity)?;
+    }
+    //A TOUR file has no weights, the parser only gives it the default.
+    if header.problem_type != ProblemType::TOUR || header.edge_weight_type != EdgeWeightType::EUC_2D
+    {
+        field(out, "EDGE_WEIGHT_TYPE", &header.edge_weight_type)?;
+    }
+    if let Some(ewf) = &header.edge_weight_format {
+        field(out, "EDGE_WEIGHT_FORMAT", ewf)?;
+    }
+    if let Some(edf) = &header.edge_data_format {
+        field(out, "EDGE_DATA_FORMAT", edf)?;
+    }
+    //These two are the defaults for a file without coordinates.
+    if header.node_coord_type != NodeCoordType::NO_COORDS {
+        field(out, "NODE_COORD_TYPE", &header.node_coord_type)?;
+    }
+    if header.display_data_type != DisplayDataType::NO_DISPLAY
+        || data.node_coordinates.is_some()
+        || data.display_data.is_some()
+    {
+        field(out, "DISPLAY_DATA_TYPE", &header.display_data_type)?;
+    }
+    for (key, value) in &header.extra {
+        field(out, key, value)?;
+    }
+    Ok(())
+}
+
+fn write_coords<W: Write>(
+    out: &mut W,
+    title: &str,
+    coords: &[Coord],
+    options: &WriteOptions,
+) -> io::Result<()> {
+    writeln!(out, "{}", title)?;
+    for coord in coords {
+        let numbers = match *coord {
+            Coord::Coord2(id, x, y) => vec![
+                options.pad(&id.to_string()),
+                options.real(x.raw()),
+                options.real(y.raw()),
+            ],
+            Coord::Coord3(id, x, y, z) => vec![
+                options.pad(&id.to_string()),
+                options.real(x.raw()),
+                options.real(y.raw()),
+                options.real(z.raw()),
+            ],
+        };
+        row(out, &numbers)?;
+    }
+    Ok(())
+}
+
+//The weights go a row of the matrix to a line, or a column for the *_COL formats, following
+//the EDGE_WEIGHT_FORMAT. When they don't fill it, they go `dimension` to a line.
+fn write_weights<W: Write>(
+    out: &mut W,
+    header: &TSPLMeta,
+    weights: &[EdgeWeight],
+    options: &WriteOptions,
+) -> io::Result<()> {
+    writeln!(out, "EDGE_WEIGHT_SECTION")?;
+    let n = header.dimension as usize;
+    let ewf = header.edge_weight_format.as_ref();
+    let lines: Vec<&[EdgeWeight]> = match ewf.and_then(|ewf| Some((ewf, positions(ewf, n)?))) {
+        Some((ewf, positions)) if entry_count(ewf, n) == Some(weights.len()) => {
+            let by_column = matches!(
+                ewf,
+                EdgeWeightFormat::UPPER_COL
+                    | EdgeWeightFormat::LOWER_COL
+                    | EdgeWeightFormat::UPPER_DIAG_COL
+                    | EdgeWeightFormat::LOWER_DIAG_COL
+            );
+            let mut lengths = vec![0; n];
+            for (r, c) in positions {
+                lengths[if by_column { c } else { r }] += 1;
+            }
+            let mut rest = weights;
+            let mut lines = vec![];
+            for length in lengths.into_iter().filter(|&length| length > 0) {
+                let (line, next) = rest.split_at(length);
+                lines.push(line);
+                rest = next;
+            }
+            lines
+        }
+        _ => weights.chunks(n.max(1)).collect(),
+    };
+    for line in lines {
+        let numbers: Vec<_> = line.iter().map(|w| options.pad(&w.to_string())).collect();
+        row(out, &numbers)?;
+    }
+    Ok(())
+}
+
+fn write_sections<W: Write>(
+    out: &mut W,
+    header: &TSPLMeta,
+    data: &TSPLData,
+    options: &WriteOptions,
+) -> io::Result<()> {
+    let node = |node: usize| options.pad(&node.to_string());
+    if let Some(coords) = &data.node_coordinates {
+        write_coords(out, "NODE_COORD_SECTION", coords, options)?;
+    }
+    if let Some(edges) = &data.edges {
+        writeln!(out, "EDGE_DATA_SECTION")?;
+        for (i, edge) in edges.iter().enumerate() {
+            match edge {
+                EdgeData::Edge((from, to)) => row(out, &[node(*from), node(*to)])?,
+                //Nodes without neighbours are left out, like in the TSPLIB files.
+                EdgeData::Adj(adj) if adj.is_empty() => {}
+                EdgeData::Adj(adj) => {
+                    let mut numbers = vec![node(i + 1)];
+                    numbers.extend(adj.iter().map(|&to| node(to)));
+                    numbers.push(options.pad("-1"));
+                    row(out, &numbers)?
+                }
+            }
+        }
+        writeln!(out, "-1")?;
+    }
+    if let Some(weights) = &data.edge_weights {
+        write_weights(out, header, weights, options)?;
+    }
+    if let Some(fixed_edges) = &data.fixed_edges {
+        writeln!(out, "FIXED_EDGES_SECTION")?;
+        for &(from, to) in fixed_edges {
+            row(out, &[node(from), node(to)])?;
+        }
+        writeln!(out, "-1")?;
+    }
+    if let Some(coords) = &data.display_data {
+        write_coords(out, "DISPLAY_DATA_SECTION", coords, options)?;
+    }
+    if let Some(demands) = &data.demands {
+        writeln!(out, "DEMAND_SECTION")?;
+        for (i, demand) in demands.iter().enumerate() {
+            row(out, &[node(i + 1), options.pad(&demand.to_string())])?;
+        }
+    }
+    if let Some(depots) = &data.depots {
+        writeln!(out, "DEPOT_SECTION")?;
+        for &depot in depots {
+            row(out, &[node(depot)])?;
+        }
+        writeln!(out, "-1")?;
+    }
+    if let Some(tours) = &data.tours {
+        writeln!(out, "TOUR_SECTION")?;
+        for tour in tours {
+            write_nodes(out, tour, TOUR_NODES_PER_LINE, options.width)?;
+            writeln!(out, "-1")?;
+        }
+        //The list of tours ends with a -1 of its own.
+        writeln!(out, "-1")?;
+    }
+    Ok(())
+}
+
+///Writes a problem out in the TSPLIB format, with a header line for every field of `TSPLMeta`
+///that isn't a default, and a section for every part of `TSPLData` there is. Parsing what it
+///writes gives back the same problem, unless `precision` rounds the coordinates.
+pub fn write_problem<W: Write>(
+    problem: &TSPLProblem,
+    writer: W,
+    options: &WriteOptions,
+) -> io::Result<()> {
+    let mut out = writer;
+    write_header(&mut out, &problem.header, &problem.data)?;
+    write_sections(&mut out, &problem.header, &problem.data, options)?;
+    writeln!(out, "EOF")?;
+    out.flush()
+}
+
+///Writes a problem to a file, see `write_problem`.
+pub fn write_path<P: AsRef<Path>>(
+    problem: &TSPLProblem,
+    path: P,
+    options: &WriteOptions,
+) -> io::Result<()> {
+    write_problem(problem, BufWriter::new(File::create(path)?), options)
+}
+
+///Writes a problem to a `String`, see `write_problem`.
+pub fn write_string(problem: &TSPLProblem, options: &WriteOptions) -> String {
+    let mut out = vec![];
+    //Writing to a Vec doesn't fail, and everything we write is UTF-8.
+    write_problem(problem, &mut out, options).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+///Writes the problem in the TSPLIB format with the default `WriteOptions`, so that
+///`parse_str(&problem.to_string())` gives it back.
+impl fmt::Display for TSPLProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&write_string(self, &WriteOptions::default()))
+    }
+}
+
+///How `write_tour` writes a tour out as a TOUR file.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct TourOptions {
+    ///The `NAME` of the file, like `berlin52.opt.tour`. It is left out when empty.
+    pub name: String,
+    ///The `COMMENT` of the file. It is left out when empty, unless there is a `length`.
+    pub comment: String,
+    ///The length of the tour, which goes in the `COMMENT` as `Length = 7542`.
+    pub length: Option<u64>,
+    ///The tour counts its nodes from 0, like the rest of this crate, instead of from 1 like
+    ///TSPLIB. They are written from 1 either way.
+    pub zero_based: bool,
+    ///The nodes go this many to a line, padded so that they line up. 0 puts the whole tour
+    ///on one line.
+    pub nodes_per_line: usize,
+    ///The `DIMENSION` of the problem the tour is for, which it has to visit every node of.
+    ///`None` takes it from the length of the tour.
+    pub dimension: Option<usize>,
+}
+
+///Writes a tour as a TOUR file: its `NAME`, `TYPE: TOUR`, `DIMENSION` and `COMMENT`, then the
+///nodes in a `TOUR_SECTION` that ends with -1.
+///
+///Fails with `InvalidInput` if the tour doesn't visit every node exactly once: when it
+///doesn't have `dimension` nodes, when a node is out of range (0 for a tour that counts
+///from 1, or past the `dimension`), or when it visits a node twice.
+pub fn write_tour<W: Write>(tour: &[usize], writer: W, options: &TourOptions) -> io::Result<()> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+    let n = options.dimension.unwrap_or(tour.len());
+    if tour.len() != n {
+        let message = format!(
+            "the tour visits {} nodes, but DIMENSION is {}",
+            tour.len(),
+            n
+        );
+        return Err(invalid(message));
+    }
+    let offset = if options.zero_based { 1 } else { 0 };
+    let nodes: Vec<usize> = tour.iter().map(|&node| node + offset).collect();
+    //Where each node is in the tour.
+    let mut position = vec![None; n];
+    for (i, &node) in nodes.iter().enumerate() {
+        let slot = node.checked_sub(1).and_then(|v| position.get_mut(v));
+        let slot = slot.ok_or_else(|| {
+            let message = format!(
+                "the tour has node {}, but the nodes go from 1 to {}",
+                node - offset,
+                n
+            );
+            invalid(message)
+        })?;
+        if slot.replace(i).is_some() {
+            let message = format!("the tour visits node {} more than once", node - offset);
+            return Err(invalid(message));
+        }
+    }
+
+    let mut out = writer;
+    if !options.name.is_empty() {
+        field(&mut out, "NAME", &options.name)?;
+    }
+    field(&mut out, "TYPE", ProblemType::TOUR)?;
+    let mut comment = options.comment.clone();
+    if let Some(length) = options.length {
+        if !comment.is_empty() {
+            comment += ", ";
+        }
+        comment += &format!("Length = {}", length);
+    }
+    if !comment.is_empty() {
+        field(&mut out, "COMMENT", comment)?;
+    }
+    field(&mut out, "DIMENSION", n)?;
+    writeln!(out, "TOUR_SECTION")?;
+    write_nodes(&mut out, &nodes, options.nodes_per_line, 0)?;
+    writeln!(out, "-1")?;
+    writeln!(out, "EOF")?;
+    out.flush()
+}
+
+///Writes a tour to a file, see `write_tour`.
+pub fn write_tour_path<P: AsRef<Path>>(
+    tour: &[usize],
+    path: P,
+    options: &TourOptions,
+) -> io::Result<()> {
+    write_tour(tour, BufWriter::new(File::create(path)?), options)
+}
+
+///Writes a tour to a `String`, see `write_tour`.
+pub fn write_tour_string(tour: &[usize], options: &TourOptions) -> io::Result<String> {
+    let mut out = vec![];
+    write_tour(tour, &mut out, options)?;
+    //Everything we write is UTF-8.
+    Ok(String::from_utf8(out).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_str;
+
+    #[test]
+    fn test_write_problem() {
+        let input = "NAME: small
+TYPE: CVRP
+COMMENT: three nodes
+DIMENSION: 3
+CAPACITY: 10
+EDGE_WEIGHT_TYPE: EXPLICIT
+EDGE_WEIGHT_FORMAT: UPPER_COL
+EDGE_DATA_FORMAT: ADJ_LIST
+DISPLAY_DATA_TYPE: TWOD_DISPLAY
+VEHICLES: 2
+EDGE_DATA_SECTION
+1 2 3 -1
+3 1 -1
+-1
+EDGE_WEIGHT_SECTION
+1
+2 3
+FIXED_EDGES_SECTION
+1 2
+-1
+DISPLAY_DATA_SECTION
+1 0 0.5
+2 1 1
+3 2 0.25
+DEMAND_SECTION
+1 0
+2 4
+3 5
+DEPOT_SECTION
+1
+-1
+EOF
+";
+        let p = parse_str(input).unwrap();
+        assert_eq!(write_string(&p, &WriteOptions::default()), input);
+        assert_eq!(p.to_string(), input);
+    }
+
+    #[test]
+    fn test_number_formatting() {
+        let input = "NAME: coords
+TYPE: TSP
+DIMENSION: 2
+EDGE_WEIGHT_TYPE: EUC_2D
+NODE_COORD_SECTION
+1 0.125 10
+2 3 -4.5
+EOF
+";
+        let p = parse_str(input).unwrap();
+        let options = WriteOptions {
+            precision: Some(2),
+            width: 6,
+        };
+        let written = write_string(&p, &options);
+        let rows: Vec<_> = written.lines().skip(7).take(2).collect();
+        assert_eq!(rows, ["     1   0.12  10.00", "     2   3.00  -4.50"]);
+        assert_eq!(parse_str(&written).unwrap().header, p.header);
+    }
+
+    #[test]
+    fn test_weight_rows_follow_the_format() {
+        let weights = |format: &str, rows: &str| {
+            let input = format!(
+                "NAME: w\nTYPE: TSP\nDIMENSION: 3\nEDGE_WEIGHT_TYPE: EXPLICIT\n\
+                 EDGE_WEIGHT_FORMAT: {}\nEDGE_WEIGHT_SECTION\n{}EOF\n",
+                format, rows
+            );
+            let p = parse_str(&input).unwrap();
+            assert_eq!(p.to_string(), input);
+        };
+        weights("FULL_MATRIX", "0 1 2\n1 0 3\n2 3 0\n");
+        weights("UPPER_ROW", "1 2\n3\n");
+        weights("LOWER_ROW", "1\n2 3\n");
+        weights("UPPER_DIAG_ROW", "0 1 2\n0 3\n0\n");
+        weights("LOWER_DIAG_COL", "0 1 2\n0 3\n0\n");
+        weights("UPPER_DIAG_COL", "0\n1 0\n2 3 0\n");
+    }
+
+    #[test]
+    fn test_tour_section_wraps() {
+        let nodes: Vec<String> = (1..=18).map(|node| node.to_string()).collect();
+        let input = format!(
+            "TYPE: TOUR\nDIMENSION: 18\nTOUR_SECTION\n{}\n-1\n-1\nEOF\n",
+            nodes.join("\n")
+        );
+        let written = parse_str(&input).unwrap().to_string();
+        let section: Vec<_> = written
+            .lines()
+            .skip_while(|l| *l != "TOUR_SECTION")
+            .collect();
+        assert_eq!(
+            section,
+            [
+                "TOUR_SECTION",
+                " 1  2  3  4  5  6  7  8  9 10 11 12 13 14 15 16",
+                "17 18",
+                "-1",
+                "-1",
+                "EOF"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write_tour() {
+        let options = TourOptions {
+            name: "five.tour".to_string(),
+            comment: "made up".to_string(),
+            length: Some(42),
+            zero_based: false,
+            nodes_per_line: 2,
+            dimension: Some(5),
+        };
+        let written = write_tour_string(&[1, 3, 5, 2, 4], &options).unwrap();
+        assert_eq!(
+            written,
+            "NAME: five.tour
+TYPE: TOUR
+COMMENT: made up, Length = 42
+DIMENSION: 5
+TOUR_SECTION
+1 3
+5 2
+4
+-1
+EOF
+"
+        );
+        //The same tour counted from 0.
+        let zero_based = TourOptions {
+            zero_based: true,
+            ..options.clone()
+        };
+        let from_zero = write_tour_string(&[0, 2, 4, 1, 3], &zero_based).unwrap();
+        assert_eq!(from_zero, written);
+        let tour = parse_str(&written).unwrap();
+        assert_eq!(tour.data.tours, Some(vec![vec![1, 3, 5, 2, 4]]));
+
+        //Node ids are padded to line up, and the comment can be just the length.
+        let options = TourOptions {
+            length: Some(7),
+            nodes_per_line: 4,
+            ..TourOptions::default()
+        };
+        let tour: Vec<usize> = (1..=10).rev().collect();
+        let written = write_tour_string(&tour, &options).unwrap();
+        let lines: Vec<_> = written.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "TYPE: TOUR",
+                "COMMENT: Length = 7",
+                "DIMENSION: 10",
+                "TOUR_SECTION",
+                "10  9  8  7",
+                " 6  5  4  3",
+                " 2  1",
+                "-1",
+                "EOF"
+            ]
+        );
+
+        let out_of_range = write_tour_string(&[0, 1, 2], &TourOptions::default()).unwrap_err();
+        assert_eq!(out_of_range.kind(), io::ErrorKind::InvalidInput);
+        assert!(write_tour_string(&[0, 1, 3], &zero_based).is_err());
+
+        //Only a visit of every node once is a tour.
+        let repeated = write_tour_string(&[1, 1, 2], &TourOptions::default()).unwrap_err();
+        assert_eq!(repeated.kind(), io::ErrorKind::InvalidInput);
+        let five = TourOptions {
+            dimension: Some(5),
+            ..TourOptions::default()
+        };
+        let too_short = write_tour_string(&[1, 3, 5, 2], &five).unwrap_err();
+        assert_eq!(too_short.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(
+            too_short.to_string(),
+            "the tour visits 4 nodes, but DIMENSION is 5"
+        );
+    }
+}
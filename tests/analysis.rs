@@ -0,0 +1,62 @@
+mod common;
+
+use common::*;
+use tsplib::*;
+
+#[test]
+fn br17_report() {
+    let r = parse("examples/allatsp/br17.atsp").weight_report().unwrap();
+    assert_eq!(r.dimension, 17);
+    assert!(!r.claimed_symmetric);
+    assert_eq!(r.diagonal_values, vec![9999]);
+    assert_eq!(r.big_m, Some(9999));
+    assert!(r.big_m_edges.is_empty());
+    assert_eq!(r.zero_weight_edges.count, 36);
+    assert_eq!(r.zero_weight_edges.examples.len(), 10);
+    assert_eq!(r.triangle_violations.count, 488);
+    for v in r.triangle_violations.examples.iter() {
+        assert!(u64::from(v.direct) > v.through_via);
+    }
+}
+
+#[test]
+fn ftv33_report() {
+    let r = parse("examples/allatsp/ftv33.atsp")
+        .weight_report()
+        .unwrap();
+    assert!(!r.symmetric);
+    assert_eq!(r.diagonal_values, vec![0, 100000000]);
+    assert_eq!(r.big_m, Some(100000000));
+    //The 100000000 on the diagonal is left out, so the rest is a proper quasi-metric.
+    assert!(r.triangle_violations.is_empty());
+}
+
+#[test]
+fn euclidean_report() {
+    let r = parse("tests/testdata/berlin52.tsp")
+        .weight_report()
+        .unwrap();
+    assert!(r.claimed_symmetric);
+    assert!(r.symmetric);
+    assert!(r.asymmetric_pairs.is_empty());
+    assert_eq!(r.diagonal_values, vec![0]);
+    assert_eq!(r.big_m, None);
+    //Rounding to the nearest integer can break the triangle inequality by 1, but no more.
+    for v in r.triangle_violations.examples.iter() {
+        assert_eq!(u64::from(v.direct), v.through_via + 1);
+    }
+}
+
+#[test]
+fn symmetric_claim_broken() {
+    let mut p = parse("tests/testdata/gr17.tsp");
+    p.convert_edge_weight_format(EdgeWeightFormat::FULL_MATRIX)
+        .unwrap();
+    //Make the weight from node 0 to 1 differ from 1 to 0.
+    p.data.edge_weights.as_mut().unwrap()[1] += 1;
+    let r = p.weight_report().unwrap();
+    assert!(r.claimed_symmetric);
+    assert!(!r.symmetric);
+    assert_eq!(r.asymmetric_pairs.count, 1);
+    assert_eq!(r.asymmetric_pairs.examples, vec![(0, 1)]);
+}
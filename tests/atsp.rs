@@ -0,0 +1,46 @@
+mod common;
+
+use common::*;
+use tsplib::*;
+
+//A few tours that start at node 0: in order, backwards, and jumping around.
+fn some_tours(n: usize) -> Vec<Tour> {
+    let forward: Tour = (0..n).collect();
+    let backward: Tour = std::iter::once(0).chain((1..n).rev()).collect();
+    let jumping: Tour = (0..n).map(|i| i * 7 % n).collect();
+    vec![forward, backward, jumping]
+}
+
+#[test]
+fn tours_map_both_ways() {
+    for name in ["br17", "ftv33"].iter() {
+        let atsp = parse(&format!("examples/allatsp/{}.atsp", name));
+        let atsp_oracle = atsp.distance_oracle().unwrap();
+        let t = atsp.to_symmetric().unwrap();
+        let n = t.atsp_dimension();
+        assert_eq!(n, atsp.header.dimension as usize);
+        assert_eq!(t.problem.header.problem_type, ProblemType::TSP);
+        assert_eq!(t.problem.header.dimension as usize, 2 * n);
+
+        let sym_oracle = t.problem.distance_oracle().unwrap();
+        assert!(sym_oracle.is_symmetric(), "{}", name);
+        for tour in some_tours(n) {
+            let sym_tour = t.to_symmetric_tour(&tour);
+            let sym_cost = tour_cost(&sym_tour, |a, b| sym_oracle.dist(a, b));
+            assert_eq!(
+                t.atsp_cost(sym_cost),
+                tour_cost(&tour, |a, b| atsp_oracle.dist(a, b)),
+                "{}",
+                name
+            );
+            assert_eq!(t.to_atsp_tour(&sym_tour), Ok(tour.clone()), "{}", name);
+
+            //The same tour walked the other way, starting somewhere else.
+            let mut reversed = sym_tour.clone();
+            reversed.reverse();
+            reversed.rotate_left(3);
+            assert_eq!(tour_cost(&reversed, |a, b| sym_oracle.dist(a, b)), sym_cost);
+            assert_eq!(t.to_atsp_tour(&reversed), Ok(tour), "{}", name);
+        }
+    }
+}
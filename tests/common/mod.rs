@@ -0,0 +1,103 @@
+//Helpers shared by the integration tests. Every test file is a crate of its own that
+//only uses some of them.
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::PathBuf;
+use tsplib::*;
+
+//The tests that go over every bundled file skip the problems with more nodes than this
+//where they do something per node or per pair of nodes, since the largest instances take
+//a while in debug builds.
+pub const QUICK_DIMENSION: u32 = 1000;
+
+//The optimal tour lengths published with TSPLIB.
+pub const OPTIMA: [(&str, u64); 32] = [
+    ("a280", 2579),
+    ("att48", 10628),
+    ("bayg29", 1610),
+    ("bays29", 2020),
+    ("berlin52", 7542),
+    ("brg180", 1950),
+    ("ch130", 6110),
+    ("ch150", 6528),
+    ("eil101", 629),
+    ("eil51", 426),
+    ("eil76", 538),
+    ("fri26", 937),
+    ("gr120", 6942),
+    ("gr202", 40160),
+    ("gr24", 1272),
+    ("gr48", 5046),
+    ("gr666", 294358),
+    ("gr96", 55209),
+    ("kroA100", 21282),
+    ("kroC100", 20749),
+    ("kroD100", 21294),
+    ("lin105", 14379),
+    ("pa561", 2763),
+    ("pcb442", 50778),
+    ("pr1002", 259045),
+    ("pr2392", 378032),
+    ("pr76", 108159),
+    ("rd100", 7910),
+    ("st70", 675),
+    ("tsp225", 3916),
+    ("ulysses16", 6859),
+    ("ulysses22", 7013),
+];
+
+//The published optimum of a problem of OPTIMA.
+pub fn optimum(name: &str) -> u64 {
+    OPTIMA.iter().find(|&&(n, _)| n == name).unwrap().1
+}
+
+pub fn parse(path: &str) -> TSPLProblem {
+    parse_problem(&fs::read_to_string(path).unwrap()).unwrap().1
+}
+
+//A problem of examples/alltsp, by its name.
+pub fn problem(name: &str) -> TSPLProblem {
+    parse(&format!("examples/alltsp/problems/{}.tsp", name))
+}
+
+//Reads the TOUR_SECTION of an .opt.tour file up to the -1 terminator,
+//and returns the (1-based) node ids.
+pub fn opt_tour(name: &str) -> Vec<usize> {
+    let contents =
+        fs::read_to_string(format!("examples/alltsp/solutions/{}.opt.tour", name)).unwrap();
+    let section = contents.split("TOUR_SECTION").nth(1).unwrap();
+    section
+        .split_whitespace()
+        .map(|n| n.parse::<i64>().unwrap())
+        .take_while(|&n| n != -1)
+        .map(|n| n as usize)
+        .collect()
+}
+
+//The length of `tour` back to where it started, with `dist` giving the weight between two
+//of its nodes.
+pub fn tour_cost<F: Fn(usize, usize) -> EdgeWeight>(tour: &[usize], dist: F) -> u64 {
+    tour.iter()
+        .zip(tour.iter().cycle().skip(1))
+        .map(|(&a, &b)| u64::from(dist(a, b)))
+        .sum()
+}
+
+//Every TSPLIB file that comes with the crate, sorted.
+pub fn bundled_files() -> Vec<PathBuf> {
+    let dirs = [
+        "tests/testdata",
+        "examples/alltsp/problems",
+        "examples/alltsp/solutions",
+        "examples/allatsp",
+    ];
+    let mut paths = vec![];
+    for dir in dirs.iter() {
+        for entry in fs::read_dir(dir).unwrap() {
+            paths.push(entry.unwrap().path());
+        }
+    }
+    paths.sort();
+    paths
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use common::*;
+use tsplib::*;
+
+#[test]
+fn compact_coordinates() {
+    let p = parse("examples/alltsp/problems/pr2392.tsp");
+    let oracle = p.distance_oracle().unwrap();
+    let m = p.compact_matrix(4).unwrap();
+    assert!(m.is_symmetric());
+    assert_eq!(m.dimension(), 2392);
+    assert_eq!(m.memory_bytes(), 2392 * 2393 / 2 * m.cell_width());
+    for i in (0..2392).step_by(7) {
+        for j in 0..2392 {
+            assert_eq!(m.dist(i, j), oracle.dist(i, j));
+        }
+    }
+}
+
+#[test]
+fn thread_count_does_not_matter() {
+    let p = parse("examples/alltsp/problems/a280.tsp");
+    let coords = p.data.node_coordinates.unwrap();
+    let one = CompactMatrix::from_coords(EdgeWeightType::EUC_2D, &coords, 1).unwrap();
+    for threads in [2, 3, 8, 1000].iter() {
+        let many = CompactMatrix::from_coords(EdgeWeightType::EUC_2D, &coords, *threads).unwrap();
+        assert_eq!(one, many);
+    }
+}
+
+#[test]
+fn compact_explicit() {
+    let p = parse("examples/allatsp/br17.atsp");
+    let m = p.compact_matrix(2).unwrap();
+    let full = p.edge_weight_matrix().unwrap();
+    assert!(!m.is_symmetric());
+    assert_eq!(m.cell_width(), 2);
+    for (i, row) in full.iter().enumerate() {
+        for (j, &w) in row.iter().enumerate() {
+            assert_eq!(m.dist(i, j), w);
+        }
+    }
+}
@@ -0,0 +1,41 @@
+mod common;
+
+use common::*;
+
+fn check_opt_tour(name: &str) {
+    let p = problem(name);
+    let tour = opt_tour(name);
+    assert_eq!(tour.len(), p.header.dimension as usize, "{}", name);
+    let length = tour_cost(&tour, |a, b| p.distance(a - 1, b - 1).unwrap());
+    assert_eq!(length, optimum(name), "{}", name);
+}
+
+#[test]
+fn euc_2d_opt_tours() {
+    let known = [
+        "a280", "berlin52", "ch130", "ch150", "eil101", "eil51", "eil76", "kroA100", "kroC100",
+        "kroD100", "lin105", "pcb442", "pr1002", "pr2392", "pr76", "rd100", "st70", "tsp225",
+    ];
+    for name in known.iter() {
+        check_opt_tour(name);
+    }
+}
+
+#[test]
+fn geo_opt_tours() {
+    let known = ["gr202", "gr666", "gr96", "ulysses16", "ulysses22"];
+    for name in known.iter() {
+        check_opt_tour(name);
+    }
+}
+
+#[test]
+fn att_opt_tour() {
+    check_opt_tour("att48");
+}
+
+#[test]
+fn distance_is_none_without_coordinates() {
+    let p = problem("gr24");
+    assert_eq!(p.distance(0, 1), None);
+}
@@ -0,0 +1,67 @@
+use std::fs;
+use tsplib::*;
+
+#[test]
+fn errors_carry_the_path() {
+    //rd100.opt.tour has no DIMENSION, which is only fine when parsing leniently.
+    let strict = ParseOptions { strict: true };
+    let path = "examples/alltsp/solutions/rd100.opt.tour";
+    let e = parse_path_with(path, &strict).unwrap_err();
+    assert_eq!(e.path.as_deref(), Some(path));
+    assert_eq!(
+        e.kind,
+        TsplibErrorKind::Strict(DiagnosticKind::DefaultedField {
+            key: "DIMENSION".to_string(),
+            value: "100".to_string()
+        })
+    );
+
+    let e = parse_path("examples/nothing_here.tsp").unwrap_err();
+    assert!(matches!(e.kind, TsplibErrorKind::Io(_)));
+    assert_eq!(e.line, 0);
+    assert!(parse_file("examples/nothing_here.tsp").is_none());
+}
+
+#[test]
+fn from_str_agrees_with_parse_path() {
+    let path = "tests/testdata/berlin52.tsp";
+    let contents = fs::read_to_string(path).unwrap();
+    let parsed: TSPLProblem = contents.parse().unwrap();
+    assert_eq!(parse_path(path), Ok(parsed.clone()));
+    assert_eq!(parse_file(path), Some(parsed));
+
+    let broken = contents.replace("EDGE_WEIGHT_TYPE: EUC_2D", "EDGE_WEIGHT_TYPE: EUC_4D");
+    let e = broken.parse::<TSPLProblem>().unwrap_err();
+    assert_eq!(e.line, 5);
+    assert_eq!(e.context.as_deref(), Some("EDGE_WEIGHT_TYPE"));
+}
+
+#[test]
+fn bundled_files_parse_strictly() {
+    let strict = ParseOptions { strict: true };
+    for path in ["tests/testdata/berlin52.tsp", "examples/allatsp/br17.atsp"].iter() {
+        let parsed = parse_path_with(path, &strict).unwrap();
+        assert!(parsed.diagnostics.is_empty());
+        assert_eq!(parse_path(path), Ok(parsed.problem));
+    }
+}
+
+#[test]
+fn truncated_files_are_invalid() {
+    let contents = fs::read_to_string("tests/testdata/berlin52.tsp").unwrap();
+    let truncated = contents.replace("52 1740.0 245.0\n", "");
+    let e = parse_str(&truncated).unwrap_err();
+    assert_eq!(
+        e.kind,
+        TsplibErrorKind::Invalid(vec![ValidationError::WrongCount {
+            section: "NODE_COORD_SECTION".to_string(),
+            expected: 52,
+            found: 51
+        }])
+    );
+    //The error points at the title of the section.
+    assert_eq!(
+        e.to_string(),
+        "6:1: NODE_COORD_SECTION has 51 rows, but DIMENSION is 52"
+    );
+}
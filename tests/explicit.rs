@@ -0,0 +1,101 @@
+mod common;
+
+use common::*;
+use std::fs;
+use tsplib::*;
+
+#[test]
+fn berlin52_to_explicit() {
+    let p = parse("tests/testdata/berlin52.tsp");
+    let e = p.to_explicit(EdgeWeightFormat::LOWER_DIAG_ROW).unwrap();
+    assert_eq!(e.header.name, p.header.name);
+    assert_eq!(e.header.comment, p.header.comment);
+    assert_eq!(e.header.edge_weight_type, EdgeWeightType::EXPLICIT);
+    assert_eq!(
+        e.header.edge_weight_format,
+        Some(EdgeWeightFormat::LOWER_DIAG_ROW)
+    );
+    assert_eq!(e.header.display_data_type, DisplayDataType::TWOD_DISPLAY);
+    assert_eq!(e.data.node_coordinates, None);
+    assert_eq!(e.data.display_data, p.data.node_coordinates);
+    assert_eq!(e.data.edge_weights.as_ref().unwrap().len(), 52 * 53 / 2);
+
+    let m = e.edge_weight_matrix().unwrap();
+    for (i, row) in m.iter().enumerate() {
+        for (j, &w) in row.iter().enumerate() {
+            assert_eq!(Some(w), p.distance(i, j));
+        }
+    }
+}
+
+#[test]
+fn geo_to_explicit() {
+    //The GEO formula puts every node at distance 1 from itself, but not a problem,
+    //so every layout holds the same matrix.
+    let p = parse("examples/alltsp/problems/burma14.tsp");
+    for ewf in [
+        EdgeWeightFormat::UPPER_ROW,
+        EdgeWeightFormat::LOWER_COL,
+        EdgeWeightFormat::UPPER_DIAG_ROW,
+    ]
+    .iter()
+    {
+        let m = p
+            .to_explicit(ewf.clone())
+            .unwrap()
+            .edge_weight_matrix()
+            .unwrap();
+        for (i, row) in m.iter().enumerate() {
+            for (j, &w) in row.iter().enumerate() {
+                assert_eq!(Some(w), p.distance(i, j), "{} ({}, {})", ewf, i, j);
+            }
+        }
+    }
+}
+
+#[test]
+fn explicit_diagonal_is_kept() {
+    let p = parse_str(
+        "TYPE: TSP
+DIMENSION: 2
+EDGE_WEIGHT_TYPE: EXPLICIT
+EDGE_WEIGHT_FORMAT: FULL_MATRIX
+EDGE_WEIGHT_SECTION
+5 1
+1 5
+EOF
+",
+    )
+    .unwrap();
+    assert_eq!(
+        p.to_explicit(EdgeWeightFormat::UPPER_ROW),
+        Err(TransformError::NonZeroDiagonal {
+            format: EdgeWeightFormat::UPPER_ROW,
+            node: 0,
+            weight: 5,
+        })
+    );
+    let e = p.to_explicit(EdgeWeightFormat::LOWER_DIAG_ROW).unwrap();
+    assert_eq!(e.data.edge_weights, Some(vec![5, 1, 5]));
+}
+
+#[test]
+fn examples_to_explicit() {
+    for path in fs::read_dir("examples/alltsp/problems").unwrap() {
+        let path = path.unwrap().path();
+        let p = parse_path(&path).unwrap_or_else(|e| panic!("{:?}: {}", path, e));
+        if p.header.dimension > QUICK_DIMENSION || p.data.node_coordinates.is_none() {
+            continue;
+        }
+        let e = p.to_explicit(EdgeWeightFormat::UPPER_ROW).unwrap();
+        let oracle = p.distance_oracle().unwrap();
+        let explicit = e.distance_oracle().unwrap();
+        assert_eq!(explicit.dimension(), oracle.dimension());
+        let n = oracle.dimension();
+        for i in 0..n {
+            for j in (0..n).step_by(11) {
+                assert_eq!(explicit.dist(i, j), oracle.dist(i, j), "{:?}", path);
+            }
+        }
+    }
+}
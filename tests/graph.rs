@@ -0,0 +1,46 @@
+use tsplib::*;
+
+fn problem(format: &str, section: &str) -> TSPLProblem {
+    let input = format!(
+        "NAME: square
+TYPE: TSP
+COMMENT: A 3-4-5 triangle and a point
+DIMENSION: 4
+EDGE_WEIGHT_TYPE: EUC_2D
+EDGE_DATA_FORMAT: {}
+NODE_COORD_SECTION
+1 0 0
+2 3 4
+3 6 8
+4 0 1
+EDGE_DATA_SECTION
+{}EOF
+",
+        format, section
+    );
+    parse_problem(&input).unwrap().1
+}
+
+#[test]
+fn edge_list_and_adj_list() {
+    let list = problem("EDGE_LIST", "1 2\n2 3\n3 1\n1 4\n-1\n");
+    let adj = problem("ADJ_LIST", "1 2 4 -1\n3 1 2 -1\n-1\n");
+    let g = list.sparse_graph().unwrap();
+    assert_eq!(adj.sparse_graph(), Ok(g.clone()));
+
+    assert!(!g.is_directed());
+    assert_eq!(g.dimension(), 4);
+    assert_eq!(g.edge_count(), 4);
+    assert_eq!(
+        g.edges().collect::<Vec<_>>(),
+        vec![(0, 1, 5), (0, 2, 10), (0, 3, 1), (1, 2, 5)]
+    );
+    assert_eq!(g.neighbors(2).collect::<Vec<_>>(), vec![(0, 10), (1, 5)]);
+    assert_eq!(g.neighbors(3).collect::<Vec<_>>(), vec![(0, 1)]);
+}
+
+#[test]
+fn no_edge_data() {
+    let p = parse_file("examples/alltsp/problems/berlin52.tsp").unwrap();
+    assert_eq!(p.sparse_graph(), Err(GraphError::MissingEdgeData));
+}
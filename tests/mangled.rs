@@ -0,0 +1,163 @@
+use std::fs;
+use tsplib::*;
+
+//Ways that files from other tools and editors differ from the ones in TSPLIB, none of
+//which should change what a file parses to.
+type Mangle = fn(&str) -> String;
+
+const MANGLES: [(&str, Mangle); 9] = [
+    ("crlf", crlf),
+    ("tabs", tabs),
+    ("lowercase", lowercase),
+    ("blank lines", blank_lines),
+    ("no EOF", no_eof),
+    ("extra EOF", extra_eof),
+    ("trailing -1", trailing_terminator),
+    ("byte order mark", byte_order_mark),
+    ("everything", everything),
+];
+
+fn is_title(line: &str) -> bool {
+    let line = line.trim().to_ascii_uppercase();
+    line.ends_with("_SECTION") || line == "EOF"
+}
+
+fn is_header(line: &str) -> bool {
+    line.contains(':')
+}
+
+fn map_lines(input: &str, f: impl Fn(&str) -> String) -> String {
+    input.lines().map(|line| f(line) + "\n").collect()
+}
+
+fn crlf(input: &str) -> String {
+    input
+        .lines()
+        .map(|line| line.to_string() + "\r\n")
+        .collect()
+}
+
+fn tabs(input: &str) -> String {
+    map_lines(input, |line| match line {
+        _ if is_header(line) => line.replacen(':', "\t:\t", 1),
+        _ if is_title(line) => format!("\t{}\t", line),
+        _ => line.replace(' ', "\t"),
+    })
+}
+
+//The keywords, section titles and enum values, but not the names and comments.
+fn lowercase(input: &str) -> String {
+    map_lines(input, |line| match line.find(':') {
+        Some(colon) => {
+            let (key, value) = line.split_at(colon);
+            match key.trim() {
+                "NAME" | "COMMENT" => key.to_lowercase() + value,
+                _ => line.to_lowercase(),
+            }
+        }
+        None => line.to_lowercase(),
+    })
+}
+
+fn blank_lines(input: &str) -> String {
+    let spaced = map_lines(input, |line| match line {
+        _ if is_title(line) => format!("\n \t\n{}\n", line),
+        _ => line.to_string(),
+    });
+    "\n".to_string() + &spaced + "\n\n"
+}
+
+fn no_eof(input: &str) -> String {
+    let lines: Vec<_> = input.lines().filter(|line| line.trim() != "EOF").collect();
+    lines.join("\n")
+}
+
+fn extra_eof(input: &str) -> String {
+    no_eof(input) + "\nEOF\nEOF:\neof\n"
+}
+
+//A -1 at the end of every section, as if they were all lists.
+fn trailing_terminator(input: &str) -> String {
+    let mut in_section = false;
+    let mut output = String::new();
+    for line in no_eof(input).lines() {
+        if is_title(line) {
+            if in_section {
+                output += "-1\n";
+            }
+            in_section = true;
+        }
+        output += line;
+        output += "\n";
+    }
+    output + "-1\nEOF\n"
+}
+
+fn byte_order_mark(input: &str) -> String {
+    "\u{feff}".to_string() + input
+}
+
+fn everything(input: &str) -> String {
+    let mangled = trailing_terminator(input);
+    let mangled = blank_lines(&mangled);
+    let mangled = lowercase(&mangled);
+    let mangled = tabs(&mangled);
+    let mangled = extra_eof(&mangled);
+    byte_order_mark(&crlf(&mangled))
+}
+
+#[test]
+fn mangled_files_parse_the_same() {
+    let mut count = 0;
+    for entry in fs::read_dir("tests/testdata").unwrap() {
+        let path = entry.unwrap().path();
+        let contents = fs::read_to_string(&path).unwrap();
+        let expected = parse_str(&contents).unwrap();
+        for (mangle, f) in MANGLES.iter() {
+            let mangled = f(&contents);
+            assert_eq!(
+                parse_str(&mangled).as_ref(),
+                Ok(&expected),
+                "{} with {}",
+                path.display(),
+                mangle
+            );
+            assert_eq!(
+                parse_reader(mangled.as_bytes()).as_ref(),
+                Ok(&expected),
+                "{} with {}",
+                path.display(),
+                mangle
+            );
+            //None of it is worth a diagnostic either.
+            let strict = ParseOptions { strict: true };
+            let parsed = parse_str_with(&mangled, &strict).map(|parsed| parsed.problem);
+            assert_eq!(
+                parsed.as_ref(),
+                Ok(&expected),
+                "{} with {}",
+                path.display(),
+                mangle
+            );
+            count += 1;
+        }
+    }
+    assert_eq!(count, 3 * MANGLES.len());
+}
+
+#[test]
+fn rows_can_end_with_a_terminator() {
+    let contents = fs::read_to_string("tests/testdata/gr17.tsp").unwrap();
+    let expected = parse_str(&contents).unwrap();
+    assert_eq!(parse_str(&(contents + " -1\nEOF")), Ok(expected));
+}
+
+#[test]
+fn unknown_keywords_are_kept_in_capitals() {
+    let input = "name: x\ntype: tsp\ndimension: 0\nedge_weight_type: euc_2d\nVehicles: 3\n";
+    let header = parse_str(input).unwrap().header;
+    assert_eq!(header.name, "x");
+    assert_eq!(header.problem_type, ProblemType::TSP);
+    assert_eq!(header.edge_weight_type, EdgeWeightType::EUC_2D);
+    assert_eq!(header.extra_value("VEHICLES"), Some("3"));
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use common::*;
+use tsplib::*;
+
+fn is_symmetric(m: &EdgeWeightMatrix) -> bool {
+    (0..m.len()).all(|r| (0..m.len()).all(|c| m[r][c] == m[c][r]))
+}
+
+#[test]
+fn explicit_opt_tours() {
+    let known = [
+        "bayg29", //UPPER_ROW
+        "bays29", //FULL_MATRIX
+        "brg180", //UPPER_ROW
+        "fri26",  //LOWER_DIAG_ROW
+        "gr120",  //LOWER_DIAG_ROW
+        "gr24",   //LOWER_DIAG_ROW
+        "gr48",   //LOWER_DIAG_ROW
+        "pa561",  //LOWER_DIAG_ROW
+    ];
+    for &name in known.iter() {
+        let p = problem(name);
+        let m = p.edge_weight_matrix().unwrap();
+        assert_eq!(m.len(), p.header.dimension as usize, "{}", name);
+        assert!(is_symmetric(&m), "{}", name);
+        let tour = opt_tour(name);
+        let length = tour_cost(&tour, |a, b| m[a - 1][b - 1]);
+        assert_eq!(length, optimum(name), "{}", name);
+    }
+}
+
+#[test]
+fn upper_diag_row() {
+    let p = parse("examples/alltsp/problems/si175.tsp");
+    let m = p.edge_weight_matrix().unwrap();
+    assert_eq!(m.len(), 175);
+    assert!(is_symmetric(&m));
+    assert!((0..175).all(|i| m[i][i] == 0));
+    //The first row of the file is the first row of the matrix, diagonal included.
+    let weights = p.data.edge_weights.unwrap();
+    assert_eq!(m[0][..], weights[..175]);
+}
+
+#[test]
+fn asymmetric_full_matrices() {
+    for name in ["br17", "ftv33", "rbg323", "rbg358", "rbg403", "rbg443"].iter() {
+        let p = parse(&format!("examples/allatsp/{}.atsp", name));
+        let n = p.header.dimension as usize;
+        let m = p.edge_weight_matrix().unwrap();
+        assert_eq!(m.len(), n, "{}", name);
+        assert_eq!(m.concat(), p.data.edge_weights.unwrap(), "{}", name);
+    }
+    let br17 = parse("examples/allatsp/br17.atsp")
+        .edge_weight_matrix()
+        .unwrap();
+    assert_eq!(br17[0][0], 9999);
+    assert_eq!(br17[0][1], 3);
+    assert_eq!(br17[1][0], 3);
+}
+
+#[test]
+fn wrong_entry_count() {
+    let mut p = parse("tests/testdata/gr17.tsp");
+    p.data.edge_weights.as_mut().unwrap().pop();
+    assert_eq!(
+        p.edge_weight_matrix(),
+        Err(MatrixError::WrongEntryCount {
+            format: EdgeWeightFormat::LOWER_DIAG_ROW,
+            dimension: 17,
+            expected: 153,
+            found: 152,
+        })
+    );
+}
+
+#[test]
+fn no_edge_weights() {
+    let p = parse("tests/testdata/berlin52.tsp");
+    assert_eq!(p.edge_weight_matrix(), Err(MatrixError::MissingEdgeWeights));
+}
+
+#[test]
+fn convert_between_layouts() {
+    let original = parse("tests/testdata/gr17.tsp");
+    let matrix = original.edge_weight_matrix().unwrap();
+    let mut p = original.clone();
+    for ewf in [
+        EdgeWeightFormat::FULL_MATRIX,
+        EdgeWeightFormat::UPPER_ROW,
+        EdgeWeightFormat::LOWER_COL,
+        EdgeWeightFormat::UPPER_DIAG_COL,
+        EdgeWeightFormat::LOWER_DIAG_ROW,
+    ]
+    .iter()
+    {
+        p.convert_edge_weight_format(ewf.clone()).unwrap();
+        assert_eq!(p.header.edge_weight_format, Some(ewf.clone()));
+        assert_eq!(p.edge_weight_matrix().unwrap(), matrix);
+    }
+    assert_eq!(p, original);
+}
+
+#[test]
+fn refuse_lossy_conversions() {
+    let original = parse("examples/allatsp/br17.atsp");
+    let mut p = original.clone();
+    assert_eq!(
+        p.convert_edge_weight_format(EdgeWeightFormat::LOWER_DIAG_ROW),
+        Err(TransformError::Asymmetric {
+            format: EdgeWeightFormat::LOWER_DIAG_ROW,
+            row: 3,
+            column: 2,
+        })
+    );
+    assert_eq!(p, original);
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use common::*;
+use tsplib::*;
+
+//The k closest nodes to `node`, found by looking at all of them.
+fn brute_force<O: DistanceOracle>(oracle: &O, node: usize, k: usize) -> Vec<(usize, EdgeWeight)> {
+    let mut all: Vec<(EdgeWeight, usize)> = (0..oracle.dimension())
+        .filter(|&v| v != node)
+        .map(|v| (oracle.dist(node, v), v))
+        .collect();
+    all.sort_unstable();
+    all.into_iter().take(k).map(|(d, v)| (v, d)).collect()
+}
+
+#[test]
+fn nearest_matches_brute_force() {
+    //EUC_2D, ATT, CEIL_2D, GEO and GEO with nodes all around the globe.
+    for name in ["berlin52", "att532", "dsj1000", "gr202", "gr666"].iter() {
+        let p = problem(name);
+        let oracle = p.distance_oracle().unwrap();
+        let tree = p.kd_tree().unwrap();
+        let sets = tree.nearest_candidates(8);
+        assert_eq!(sets.len(), p.header.dimension as usize);
+        for set in sets {
+            assert_eq!(
+                set.candidates,
+                brute_force(&oracle, set.node, 8),
+                "{} {}",
+                name,
+                set.node
+            );
+        }
+    }
+}
+
+#[test]
+fn quadrants_surround_the_node() {
+    let p = problem("pr2392");
+    let coords = p.data.node_coordinates.as_ref().unwrap();
+    let tree = p.kd_tree().unwrap();
+    let xy = |v: usize| match coords[v] {
+        Coord::Coord2(_, x, y) => (x, y),
+        Coord::Coord3(_, x, y, _) => (x, y),
+    };
+    for set in tree.quadrant_candidates(2) {
+        assert!(set.candidates.len() <= 8);
+        let (x, y) = xy(set.node);
+        for q in 0..4 {
+            let in_quadrant = set
+                .candidates
+                .iter()
+                .filter(|&&(v, _)| tree.quadrant(set.node, v) == q)
+                .count();
+            assert!(in_quadrant <= 2);
+            //A quadrant only comes up short when there is nothing more in it.
+            if in_quadrant < 2 {
+                let available = (0..coords.len())
+                    .filter(|&v| v != set.node && tree.quadrant(set.node, v) == q)
+                    .count();
+                assert_eq!(in_quadrant, available);
+            }
+        }
+        for &(v, _) in &set.candidates {
+            let (vx, vy) = xy(v);
+            let q = tree.quadrant(set.node, v);
+            assert_eq!(q & 1 != 0, vx < x);
+            assert_eq!(q & 2 != 0, vy < y);
+        }
+    }
+}
+
+#[test]
+fn large_instance() {
+    let p = problem("d18512");
+    let oracle = p.distance_oracle().unwrap();
+    let tree = p.kd_tree().unwrap();
+    let sets = tree.nearest_candidates(10);
+    for node in (0..18512).step_by(1000) {
+        assert_eq!(sets[node].candidates, brute_force(&oracle, node, 10));
+    }
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::*;
+use tsplib::*;
+
+#[test]
+fn coordinate_oracle() {
+    let p = parse("tests/testdata/berlin52.tsp");
+    let oracle = p.distance_oracle().unwrap();
+    assert_eq!(oracle.dimension(), 52);
+    assert!(oracle.is_symmetric());
+    for i in 0..52 {
+        for j in 0..52 {
+            assert_eq!(Some(oracle.dist(i, j)), p.distance(i, j));
+        }
+    }
+}
+
+#[test]
+fn explicit_oracle() {
+    let p = parse("tests/testdata/gr17.tsp");
+    let oracle = p.distance_oracle().unwrap();
+    let m = p.edge_weight_matrix().unwrap();
+    assert_eq!(oracle.dimension(), 17);
+    assert!(oracle.is_symmetric());
+    assert_eq!(oracle.dist(1, 0), 633);
+    assert_eq!(oracle.dist(0, 1), 633);
+    assert_eq!(oracle.dist(16, 15), m[16][15]);
+
+    let p = parse("examples/allatsp/br17.atsp");
+    let oracle = p.distance_oracle().unwrap();
+    assert!(!oracle.is_symmetric());
+}
+
+#[test]
+fn cached_oracle_matches() {
+    let p = parse("examples/alltsp/problems/a280.tsp");
+    let oracle = p.distance_oracle().unwrap();
+    let cached = CachedDistances::new(p.distance_oracle().unwrap());
+    for i in 0..280 {
+        for j in 0..280 {
+            assert_eq!(cached.dist(i, j), oracle.dist(i, j));
+            assert_eq!(cached.dist(j, i), oracle.dist(i, j));
+        }
+    }
+}
+
+#[test]
+fn missing_coordinates() {
+    let mut p = parse("tests/testdata/berlin52.tsp");
+    p.data.node_coordinates = None;
+    assert_eq!(
+        p.distance_oracle().err(),
+        Some(OracleError::MissingCoordinates)
+    );
+}
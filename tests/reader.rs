@@ -0,0 +1,55 @@
+use std::fs::{self, File};
+use std::io::{BufReader, Cursor};
+use tsplib::*;
+
+const BERLIN52: &str = "tests/testdata/berlin52.tsp";
+
+#[test]
+fn reader_agrees_with_parse_str() {
+    let contents = fs::read_to_string(BERLIN52).unwrap();
+    let expected = parse_str(&contents).unwrap();
+    let file = BufReader::new(File::open(BERLIN52).unwrap());
+    assert_eq!(parse_reader(file), Ok(expected.clone()));
+    //Line endings don't matter.
+    let crlf = contents.replace('\n', "\r\n");
+    assert_eq!(parse_reader(crlf.as_bytes()), Ok(expected));
+}
+
+#[test]
+fn read_errors_say_where() {
+    let mut input = b"NAME: x\nTYPE: TSP\nCOMMENT: ".to_vec();
+    input.extend_from_slice(&[0xff, 0xfe, b'\n']);
+    let e = parse_reader(Cursor::new(input)).unwrap_err();
+    assert!(matches!(e.kind, TsplibErrorKind::Io(_)));
+    assert_eq!(e.line, 3);
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn gzip_is_found_by_its_magic_bytes() {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    let contents = fs::read(BERLIN52).unwrap();
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder.write_all(&contents).unwrap();
+    let compressed = encoder.finish().unwrap();
+    let expected = parse_path(BERLIN52).unwrap();
+    assert_eq!(parse_reader(&compressed[..]), Ok(expected.clone()));
+
+    //The file name has nothing to do with it.
+    let path = std::env::temp_dir().join("tsplib_gzip_berlin52.tsp");
+    fs::write(&path, &compressed).unwrap();
+    let parsed = parse_path(&path);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(parsed, Ok(expected));
+}
+
+#[cfg(not(feature = "gzip"))]
+#[test]
+fn gzip_needs_the_feature() {
+    let e = parse_reader(&[0x1f, 0x8b, 0x08, 0x00][..]).unwrap_err();
+    assert!(matches!(e.kind, TsplibErrorKind::Io(_)));
+    assert!(e.message().contains("gzip feature"));
+}
@@ -0,0 +1,34 @@
+#![cfg(feature = "serde")]
+mod common;
+
+use common::*;
+use tsplib::*;
+
+#[test]
+fn bundled_files_round_trip_through_json_and_messagepack() {
+    for path in bundled_files() {
+        let p = parse_path(&path).unwrap();
+        if p.header.dimension > QUICK_DIMENSION {
+            continue;
+        }
+        let json = serde_json::to_string(&p).unwrap();
+        let from_json: TSPLProblem = serde_json::from_str(&json).unwrap();
+        assert_eq!(from_json, p, "{}", path.display());
+
+        let msgpack = rmp_serde::to_vec(&p).unwrap();
+        let from_msgpack: TSPLProblem = rmp_serde::from_slice(&msgpack).unwrap();
+        assert_eq!(from_msgpack, p, "{}", path.display());
+    }
+}
+
+#[test]
+fn coordinates_are_plain_numbers() {
+    let p = parse_path("tests/testdata/berlin52.tsp").unwrap();
+    let json = serde_json::to_value(&p).unwrap();
+    assert_eq!(
+        json["data"]["node_coordinates"][0],
+        serde_json::json!({ "Coord2": [1, 565.0, 575.0] })
+    );
+    assert_eq!(json["header"]["edge_weight_type"], "EUC_2D");
+    assert_eq!(json["header"]["display_data_type"], "COORD_DISPLAY");
+}
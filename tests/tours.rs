@@ -0,0 +1,72 @@
+mod common;
+
+use common::*;
+use std::fs;
+use tsplib::*;
+
+#[test]
+fn opt_tours_are_one_tour_of_dimension_nodes() {
+    let mut count = 0;
+    for entry in fs::read_dir("examples/alltsp/solutions").unwrap() {
+        let path = entry.unwrap().path();
+        if !path.to_string_lossy().ends_with(".opt.tour") {
+            continue;
+        }
+        let tour = parse_path(&path).unwrap();
+        let n = tour.header.dimension as usize;
+        let tours = tour.data.tours.unwrap();
+        assert_eq!(tours.len(), 1, "{}", path.display());
+        assert_eq!(tours[0].len(), n, "{}", path.display());
+        count += 1;
+    }
+    assert_eq!(count, 32);
+}
+
+#[test]
+fn wrapped_tours_are_not_split() {
+    //pr1002.opt.tour has 16 nodes on every line.
+    let tour = parse_path("examples/alltsp/solutions/pr1002.opt.tour").unwrap();
+    assert_eq!(tour.header.dimension, 1002);
+    let tours = tour.data.tours.unwrap();
+    let mut nodes = tours[0].clone();
+    nodes.sort_unstable();
+    assert_eq!(nodes, (1..=1002).collect::<Vec<_>>());
+}
+
+#[test]
+fn opt_tours_have_the_optimal_length() {
+    for &(name, optimum) in OPTIMA.iter() {
+        let problem = parse_path(format!("examples/alltsp/problems/{}.tsp", name)).unwrap();
+        let tour = problem
+            .load_tour_path(format!("examples/alltsp/solutions/{}.opt.tour", name))
+            .unwrap();
+        assert_eq!(tour.length, optimum, "{}", name);
+    }
+}
+
+#[test]
+fn written_tours_read_back() {
+    let problem = parse_path("examples/alltsp/problems/pr1002.tsp").unwrap();
+    let tour = problem
+        .load_tour_path("examples/alltsp/solutions/pr1002.opt.tour")
+        .unwrap();
+    let options = TourOptions {
+        name: "pr1002.tour".to_string(),
+        length: Some(tour.length),
+        zero_based: true,
+        nodes_per_line: 16,
+        ..TourOptions::default()
+    };
+    let written = write_tour_string(&tour.nodes, &options).unwrap();
+    let parsed = parse_str(&written).unwrap();
+    assert_eq!(parsed.header.comment, "Length = 259045");
+    assert_eq!(problem.load_tour(&parsed), Ok(tour.clone()));
+
+    //Laid out like the file it came from.
+    let original = fs::read_to_string("examples/alltsp/solutions/pr1002.opt.tour").unwrap();
+    let section = |contents: &str| {
+        let start = contents.find("TOUR_SECTION").unwrap();
+        contents[start..].to_string()
+    };
+    assert_eq!(section(&written), section(&original));
+}
@@ -1,3 +1,6 @@
+//The original tests spell out `header: header`.
+#![allow(clippy::redundant_field_names)]
+
 use noisy_float::prelude::*;
 use pretty_assertions::assert_eq;
 use std::fs;
@@ -13,14 +16,15 @@ fn berlin52() {
     let header = TSPLMeta {
         comment: "52 locations in Berlin (Groetschel)".to_string(),
         dimension: 52,
-        display_data_type: DisplayDataType::NO_DISPLAY,
+        display_data_type: DisplayDataType::COORDS_DISPLAY,
         name: "berlin52".to_string(),
         problem_type: ProblemType::TSP,
         capacity: None,
         edge_weight_type: EdgeWeightType::EUC_2D,
         edge_weight_format: None,
         edge_data_format: None,
-        node_coord_type: NodeCoordType::NO_COORDS,
+        node_coord_type: NodeCoordType::TWOD_COORDS,
+        extra: vec![],
     };
     t.node_coordinates = Some(vec![
         Coord2(1, n64(565.0), n64(575.0)),
@@ -102,6 +106,7 @@ fn gr17() {
         edge_data_format: None,
         edge_weight_format: Some(EdgeWeightFormat::LOWER_DIAG_ROW),
         node_coord_type: NodeCoordType::NO_COORDS,
+        extra: vec![],
     };
     t.edge_weights = Some(vec![
         0, 633, 0, 257, 390, 0, 91, 661, 228, 0, 412, 227, 169, 383, 0, 150, 488, 112, 120, 267, 0,
@@ -139,6 +144,7 @@ fn bays29() {
         edge_data_format: None,
         edge_weight_format: Some(EdgeWeightFormat::FULL_MATRIX),
         node_coord_type: NodeCoordType::NO_COORDS,
+        extra: vec![],
     };
     t.edge_weights = Some(vec![
         0, 107, 241, 190, 124, 80, 316, 76, 152, 157, 283, 133, 113, 297, 228, 129, 348, 276, 188,
@@ -238,13 +244,9 @@ fn parse_alltsp() {
         if !pathstr.ends_with(".tsp") {
             continue;
         };
-        let contents = fs::read_to_string(pathstr).unwrap();
-        let parsed = parse_problem(&contents);
-        // println!("parsed got {:?}", parsed);
-        match tsplib::parse_file(&pathstr) {
-            Some(_) => continue,
-            None => println!("Error parsing this one, got {:?}", parsed),
-        };
+        if let Err(e) = parse_path(pathstr) {
+            println!("Error parsing this one: {}", e);
+        }
     }
 }
 
@@ -259,12 +261,8 @@ fn parse_allatsp() {
             continue;
         };
         println!("path is: {}", pathstr);
-        let contents = fs::read_to_string(pathstr).unwrap();
-        let parsed = parse_problem(&contents);
-        // println!("parsed got {:?}", parsed);
-        match tsplib::parse_file(&pathstr) {
-            Some(_) => continue,
-            None => println!("Error parsing this one, got {:?}", parsed),
-        };
+        if let Err(e) = parse_path(pathstr) {
+            println!("Error parsing this one: {}", e);
+        }
     }
 }
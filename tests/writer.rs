@@ -0,0 +1,38 @@
+mod common;
+
+use common::*;
+use std::fs;
+use tsplib::*;
+
+#[test]
+fn every_bundled_file_round_trips() {
+    let strict = ParseOptions { strict: true };
+    let padded = WriteOptions {
+        width: 8,
+        ..WriteOptions::default()
+    };
+    let paths = bundled_files();
+    for path in &paths {
+        let p = parse_path(path).unwrap();
+        let written = write_string(&p, &WriteOptions::default());
+        //What the writer leaves out, the parser doesn't have to make up.
+        let parsed = parse_str_with(&written, &strict).map(|parsed| parsed.problem);
+        assert_eq!(parsed.as_ref(), Ok(&p), "{}", path.display());
+        if p.header.dimension > QUICK_DIMENSION {
+            continue;
+        }
+        let written = write_string(&p, &padded);
+        assert_eq!(parse_str(&written).as_ref(), Ok(&p), "{}", path.display());
+    }
+    assert_eq!(paths.len(), 165);
+}
+
+#[test]
+fn written_files_read_back() {
+    let p = parse_path("tests/testdata/bays29.tsp").unwrap();
+    let path = std::env::temp_dir().join("tsplib_write_bays29.tsp");
+    write_path(&p, &path, &WriteOptions::default()).unwrap();
+    let parsed = parse_path(&path);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(parsed, Ok(p));
+}